@@ -0,0 +1,67 @@
+//! compares the spatial grid query of active markers against the linear scan of all markers
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use glam::{Mat4, Vec3};
+use joko_marker_format::{Frustum, SpatialGrid};
+
+/// dense pack with a lot of markers spread over a 2km x 2km map
+const MARKER_COUNT: usize = 10_000;
+const MAX_DISTANCE: f32 = 300.0;
+const RADIUS: f32 = 10.0;
+
+/// simple deterministic pseudo random positions, so that every run benchmarks the same map
+fn positions() -> Vec<Vec3> {
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        (state % 2000) as f32 - 1000.0
+    };
+    (0..MARKER_COUNT)
+        .map(|_| Vec3::new(next(), next() / 10.0, next()))
+        .collect()
+}
+
+fn frustum(cam_pos: Vec3) -> Frustum {
+    let view = Mat4::look_at_lh(cam_pos, cam_pos + Vec3::X, Vec3::Y);
+    let proj = Mat4::perspective_lh(1.0, 16.0 / 9.0, 1.0, 1000.0);
+    Frustum::from_view_proj(proj * view)
+}
+
+fn culling(c: &mut Criterion) {
+    let positions = positions();
+    let cam_pos = Vec3::new(100.0, 20.0, -50.0);
+    let frustum = frustum(cam_pos);
+    let grid = SpatialGrid::with_radii(
+        SpatialGrid::DEFAULT_CELL_SIZE,
+        positions
+            .iter()
+            .enumerate()
+            .map(|(index, position)| (index, *position, RADIUS)),
+    );
+    let mut out = Vec::with_capacity(MARKER_COUNT);
+    c.bench_function("linear scan", |b| {
+        b.iter(|| {
+            out.clear();
+            for (index, position) in positions.iter().enumerate() {
+                if position.distance(cam_pos) <= MAX_DISTANCE + RADIUS
+                    && frustum.contains_sphere(*position, RADIUS)
+                {
+                    out.push(index);
+                }
+            }
+            black_box(out.len())
+        })
+    });
+    c.bench_function("spatial grid", |b| {
+        b.iter(|| {
+            out.clear();
+            grid.query_frustum(&frustum, cam_pos, MAX_DISTANCE, &mut out);
+            black_box(out.len())
+        })
+    });
+}
+
+criterion_group!(benches, culling);
+criterion_main!(benches);
@@ -6,6 +6,8 @@ pub(crate) mod io;
 pub(crate) mod manager;
 pub(crate) mod pack;
 
+pub use manager::spatial::{Frustum, SpatialGrid};
+pub use manager::timer::{Clock, SystemClock};
 pub use manager::MarkerManager;
 // for compile time build info like pkg version or build timestamp or git hash etc..
 // shadow_rs::shadow!(build);
@@ -24,7 +26,7 @@ pub fn rapid_filter_rust(src_xml: String) -> String {
     ffi::rapid_filter(src_xml)
 }
 
-pub const INCHES_PER_METER: f32 = 39.37;
+pub use jokoapi::end_point::maps::INCHES_PER_METER;
 
 pub fn is_default<T: PartialEq + Default>(t: &T) -> bool {
     t == &T::default()
@@ -0,0 +1,180 @@
+use joko_render::billboard::MarkerVertex;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+use crate::pack::CommonAttributes;
+
+/// When should a marker bounce. parsed from the `bounce` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BounceTrigger {
+    /// `bounce="always"`. The marker keeps bouncing as long as it is visible
+    Always,
+    /// any other non-empty value. The marker bounces while the player is within its trigger range
+    InRange,
+}
+impl BounceTrigger {
+    pub fn from_attr(bounce: &str) -> Option<Self> {
+        match bounce.trim().to_lowercase().as_str() {
+            "" | "0" | "none" => None,
+            "always" => Some(Self::Always),
+            _ => Some(Self::InRange),
+        }
+    }
+}
+
+/// The animation state of an active marker
+#[derive(Debug, Default, Clone)]
+pub(crate) struct MarkerAnimation {
+    /// when the current bounce started. None if the marker is not bouncing
+    bounce_start: Option<OffsetDateTime>,
+    /// The extra height (in meters) added to the marker's position this frame
+    pub bounce_height: f32,
+}
+
+impl MarkerAnimation {
+    /// default taco bounce height in meters
+    pub const DEFAULT_BOUNCE_HEIGHT: f32 = 2.0;
+    /// default taco bounce duration in seconds
+    pub const DEFAULT_BOUNCE_DURATION: f32 = 1.0;
+    /// default taco bounce delay in seconds
+    pub const DEFAULT_BOUNCE_DELAY: f32 = 0.0;
+
+    /// updates the bounce height for this frame.
+    /// `in_trigger_range` is whether the player is currently within the trigger range of the marker
+    pub fn tick(&mut self, attrs: &CommonAttributes, in_trigger_range: bool, now: OffsetDateTime) {
+        let bouncing = match attrs
+            .get_bounce()
+            .and_then(|bounce| BounceTrigger::from_attr(bounce))
+        {
+            Some(BounceTrigger::Always) => true,
+            Some(BounceTrigger::InRange) => in_trigger_range,
+            None => false,
+        };
+        if !bouncing {
+            self.bounce_start = None;
+            self.bounce_height = 0.0;
+            return;
+        }
+        let start = *self.bounce_start.get_or_insert(now);
+        self.bounce_height = bounce_offset(
+            (now - start).as_seconds_f32(),
+            attrs
+                .get_bounce_delay()
+                .copied()
+                .unwrap_or(Self::DEFAULT_BOUNCE_DELAY),
+            attrs
+                .get_bounce_duration()
+                .copied()
+                .unwrap_or(Self::DEFAULT_BOUNCE_DURATION),
+            attrs
+                .get_bounce_height()
+                .copied()
+                .unwrap_or(Self::DEFAULT_BOUNCE_HEIGHT),
+        );
+    }
+}
+
+/// The height offset of a bouncing marker `elapsed` seconds after the bounce started.
+/// Every cycle, the marker waits on the ground for `delay` seconds and then jumps up to `height` and falls back down in `duration` seconds.
+pub(crate) fn bounce_offset(elapsed: f32, delay: f32, duration: f32, height: f32) -> f32 {
+    if duration <= 0.0 {
+        return 0.0;
+    }
+    let delay = delay.max(0.0);
+    let cycle_time = elapsed.max(0.0).rem_euclid(delay + duration);
+    if cycle_time < delay {
+        return 0.0;
+    }
+    height * (std::f32::consts::PI * (cycle_time - delay) / duration).sin()
+}
+
+/// The texture offset of an animated trail `elapsed` since the animations started (a monotonic instant, so that trails never jump).
+/// trails scroll `anim_speed` texture lengths every second. we only keep the fractional part, as textures repeat anyway.
+pub(crate) fn trail_scroll_offset(elapsed: Duration, anim_speed: f32) -> f32 {
+    // f64, so that we don't lose precision after jokolay has been running for a few hours
+    (elapsed.as_secs_f64() * anim_speed as f64).fract() as f32
+}
+
+/// returns a copy of the vertices with their texture coordinates scrolled along the length of the trail (`v` axis) by `offset`
+pub(crate) fn scroll_texture_coordinates(
+    vertices: &[MarkerVertex],
+    offset: f32,
+) -> Vec<MarkerVertex> {
+    vertices
+        .iter()
+        .map(|vertex| {
+            let mut vertex = *vertex;
+            vertex.texture_coordinates.y += offset;
+            vertex
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn approx(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-4
+    }
+
+    #[test]
+    fn bounce_curve() {
+        // 0.5 seconds on the ground, then 1 second in the air upto 2 meters
+        let offset = |elapsed| bounce_offset(elapsed, 0.5, 1.0, 2.0);
+        assert_eq!(offset(0.0), 0.0);
+        assert_eq!(offset(0.4), 0.0);
+        assert!(
+            approx(offset(1.0), 2.0),
+            "peak is half way through the duration"
+        );
+        assert!(offset(0.75) > 0.0 && offset(0.75) < 2.0);
+        assert!(approx(offset(0.75), offset(1.25)), "falls like it rose");
+        // after the duration, the next cycle starts with the delay again
+        assert!(approx(offset(1.5), 0.0));
+        assert_eq!(offset(1.9), 0.0);
+        assert!(approx(offset(2.5), 2.0));
+        // invalid durations don't bounce
+        assert_eq!(bounce_offset(1.0, 0.0, 0.0, 2.0), 0.0);
+    }
+
+    #[test]
+    fn trail_scrolls_and_wraps() {
+        assert!(approx(
+            trail_scroll_offset(Duration::from_millis(250), 1.0),
+            0.25
+        ));
+        assert!(approx(
+            trail_scroll_offset(Duration::from_millis(1250), 1.0),
+            0.25
+        ));
+        assert!(approx(
+            trail_scroll_offset(Duration::from_secs(3), 0.5),
+            0.5
+        ));
+        // backwards
+        assert!(approx(
+            trail_scroll_offset(Duration::from_millis(250), -1.0),
+            -0.25
+        ));
+        // no jump or precision loss after running for a long time (past utc midnight etc..)
+        let day = Duration::from_secs(86_400);
+        assert!(approx(
+            trail_scroll_offset(day * 3 + Duration::from_millis(100), 0.3),
+            trail_scroll_offset(day * 3, 0.3) + 0.03
+        ));
+
+        let vertex = |u, v| MarkerVertex {
+            position: glam::Vec3::ONE,
+            alpha: 1.0,
+            texture_coordinates: glam::vec2(u, v),
+            fade_near_far: glam::vec2(-1.0, -1.0),
+            color: [255; 4],
+        };
+        let vertices = [vertex(0.0, -1.0), vertex(1.0, 0.5)];
+        let scrolled = scroll_texture_coordinates(&vertices, 0.25);
+        assert_eq!(scrolled[0].texture_coordinates, glam::vec2(0.0, -0.75));
+        assert_eq!(scrolled[1].texture_coordinates, glam::vec2(1.0, 0.75));
+        assert_eq!(scrolled[0].position, vertices[0].position);
+    }
+}
@@ -0,0 +1,140 @@
+use egui::{Color32, LayerId, Rect, Shape, Stroke, TextureId};
+use glam::{vec2, Mat4, Vec2, Vec3};
+
+/// Where an off-screen marker is pinned on the edge of the screen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct EdgePlacement {
+    /// center of the icon on the screen
+    pub position: Vec2,
+    /// normalized direction (in screen coordinates, y grows downwards) from the center of the screen towards the marker
+    pub direction: Vec2,
+}
+
+/// Projects `position` with `view_proj` and returns where it must be pinned on the edge of the screen.
+/// The icon is kept `margin` away from the edges. returns None if the position is on the screen.
+/// Positions behind the camera are pinned in the direction that the camera needs to turn to see them.
+pub(crate) fn edge_placement(
+    view_proj: Mat4,
+    position: Vec3,
+    screen_size: Vec2,
+    margin: f32,
+) -> Option<EdgePlacement> {
+    let clip = view_proj * position.extend(1.0);
+    if clip.w > 0.0 {
+        let ndc = clip.truncate() / clip.w;
+        if ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 {
+            return None;
+        }
+    }
+    // dividing by a negative w mirrors the position, so we just use the direction of clip space x/y
+    let direction = vec2(clip.x, -clip.y).try_normalize().unwrap_or(Vec2::Y);
+    let half_size = (screen_size / 2.0 - Vec2::splat(margin)).max(Vec2::ZERO);
+    // scale the direction until it touches either the vertical or horizontal edge
+    let scale = (half_size / direction.abs()).min_element();
+    Some(EdgePlacement {
+        position: screen_size / 2.0 + direction * scale,
+        direction,
+    })
+}
+
+/// An active marker of a category with edge herding, which will be pinned to the screen edge if it is off-screen
+pub(crate) struct EdgeMarker {
+    /// world position of the marker (with height offset)
+    pub position: Vec3,
+    pub texture: TextureId,
+    pub tint: Color32,
+}
+
+/// Collects the markers which want to stay on screen every frame and draws the off-screen ones at the edge of the screen with an arrow.
+#[derive(Default)]
+pub(crate) struct EdgeLayer {
+    pub markers: Vec<EdgeMarker>,
+}
+
+impl EdgeLayer {
+    /// size of the icon in egui points
+    const ICON_SIZE: f32 = 32.0;
+    /// length of the arrow which points towards the marker
+    const ARROW_SIZE: f32 = 12.0;
+
+    pub fn clear(&mut self) {
+        self.markers.clear();
+    }
+
+    pub fn gui(&mut self, etx: &egui::Context, joko_renderer: &joko_render::JokoRenderer) {
+        if self.markers.is_empty() {
+            return;
+        }
+        let screen_rect = etx.screen_rect();
+        let screen_size = vec2(screen_rect.width(), screen_rect.height());
+        let painter = etx.layer_painter(LayerId::background());
+        let margin = Self::ICON_SIZE / 2.0 + Self::ARROW_SIZE;
+        for marker in self.markers.iter() {
+            let Some(placement) = edge_placement(
+                joko_renderer.view_proj,
+                marker.position,
+                screen_size,
+                margin,
+            ) else {
+                continue;
+            };
+            let center = egui::pos2(placement.position.x, placement.position.y);
+            painter.image(
+                marker.texture,
+                Rect::from_center_size(center, egui::vec2(Self::ICON_SIZE, Self::ICON_SIZE)),
+                Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                marker.tint,
+            );
+            // a triangle just outside the icon, pointing towards the marker
+            let direction = placement.direction;
+            let side = direction.perp();
+            let base = placement.position + direction * Self::ICON_SIZE / 2.0;
+            let tip = base + direction * Self::ARROW_SIZE;
+            let points = [
+                tip,
+                base + side * Self::ARROW_SIZE / 2.0,
+                base - side * Self::ARROW_SIZE / 2.0,
+            ]
+            .map(|point| egui::pos2(point.x, point.y));
+            painter.add(Shape::convex_polygon(
+                points.to_vec(),
+                marker.tint,
+                Stroke::new(1.0, Color32::BLACK),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    const SCREEN: Vec2 = Vec2::new(200.0, 100.0);
+
+    fn view_proj() -> Mat4 {
+        Mat4::perspective_lh(1.0, 2.0, 1.0, 1000.0) * Mat4::look_at_lh(Vec3::ZERO, Vec3::Z, Vec3::Y)
+    }
+
+    #[test]
+    fn on_screen_markers_are_not_pinned() {
+        assert_eq!(
+            edge_placement(view_proj(), Vec3::Z * 10.0, SCREEN, 10.0),
+            None
+        );
+    }
+
+    #[test]
+    fn off_screen_markers_are_pinned_to_the_edge() {
+        // far to the right, in front of the camera
+        let placement = edge_placement(view_proj(), vec3(100.0, 0.0, 10.0), SCREEN, 10.0).unwrap();
+        assert!((placement.position - vec2(190.0, 50.0)).length() < 0.001);
+        assert!((placement.direction - Vec2::X).length() < 0.001);
+        // behind and to the left of the camera. left handed, so -x is left
+        let placement = edge_placement(view_proj(), vec3(-10.0, 0.0, -10.0), SCREEN, 10.0).unwrap();
+        assert!((placement.position - vec2(10.0, 50.0)).length() < 0.001);
+        // above the camera
+        let placement = edge_placement(view_proj(), vec3(0.0, 100.0, 1.0), SCREEN, 10.0).unwrap();
+        assert!((placement.position - vec2(100.0, 10.0)).length() < 0.001);
+    }
+}
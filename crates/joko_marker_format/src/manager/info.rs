@@ -0,0 +1,278 @@
+use egui::{Align2, Color32, FontId, LayerId};
+use glam::{vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+use super::timer::format_countdown;
+use crate::INCHES_PER_METER;
+
+/// The unit used to show the distances of markers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DistanceUnit {
+    #[default]
+    Meters,
+    /// inches. the unit of the positions in marker packs and the gw2 api
+    GameUnits,
+}
+
+/// formats a distance (in meters) like `123 m` or `4843 in`
+pub(crate) fn format_distance(distance: f32, unit: DistanceUnit) -> String {
+    match unit {
+        DistanceUnit::Meters => format!("{distance:.0} m"),
+        DistanceUnit::GameUnits => format!("{:.0} in", distance * INCHES_PER_METER),
+    }
+}
+
+/// horizontal angle (in degrees) between the camera's direction and the direction from `from` to `to`.
+/// negative is to the left, positive is to the right. ranges from -180 to 180.
+pub(crate) fn bearing(camera_front: Vec3, from: Vec3, to: Vec3) -> f32 {
+    let front = vec2(camera_front.x, camera_front.z);
+    let direction = vec2(to.x - from.x, to.z - from.z);
+    // left handed with y up, so the right of the camera is front rotated clockwise
+    let right = vec2(front.y, -front.x);
+    direction
+        .dot(right)
+        .atan2(direction.dot(front))
+        .to_degrees()
+}
+
+/// formats a bearing like `ahead`, `30° left` or `120° right`
+fn format_bearing(bearing: f32) -> String {
+    let degrees = bearing.abs().round();
+    if degrees < 1.0 {
+        "ahead".to_string()
+    } else if bearing < 0.0 {
+        format!("{degrees:.0}° left")
+    } else {
+        format!("{degrees:.0}° right")
+    }
+}
+
+/// The info text of a marker, when the player is within the `infoRange` of that marker
+pub(crate) struct MarkerInfo {
+    /// distance of the marker from the player. used to sort the stack of info texts
+    pub distance: f32,
+    pub info: SmolStr,
+}
+
+/// The title of a marker which is drawn just above its billboard
+pub(crate) struct MarkerTitle {
+    /// world position of the top edge of the billboard
+    pub position: Vec3,
+    pub title: SmolStr,
+    /// sRGBA8. unmultiplied
+    pub color: [u8; 4],
+}
+
+/// A live countdown of a sleeping marker (until it reappears) or of the next spawn of an event timer marker
+pub(crate) struct MarkerCountdown {
+    /// world position at which the countdown is drawn. None if the marker is not drawn this frame, so it is only in the timers list
+    pub position: Option<Vec3>,
+    /// name of the marker shown in the timers list
+    pub label: String,
+    pub remaining: time::Duration,
+}
+
+/// The distance of a marker from the player, drawn just below its billboard
+pub(crate) struct MarkerLabel {
+    /// world position of the bottom edge of the billboard
+    pub position: Vec3,
+    /// distance from the player in meters
+    pub distance: f32,
+    /// alpha of the billboard, so that the label fades with the marker
+    pub alpha: f32,
+    /// the marker also shows a countdown below its billboard, so the label goes below the countdown
+    pub below_countdown: bool,
+}
+
+/// An active marker close to the player for the closest markers list
+pub(crate) struct NearbyMarker {
+    pub label: String,
+    /// distance from the player in meters
+    pub distance: f32,
+    /// see [bearing]
+    pub bearing: f32,
+}
+
+/// This collects the info/title text of active markers from all packs every frame and draws them with egui.
+/// Info texts of overlapping markers are stacked in the order of their distance from the player (closest first).
+#[derive(Default)]
+pub(crate) struct InfoLayer {
+    pub infos: Vec<MarkerInfo>,
+    pub titles: Vec<MarkerTitle>,
+    pub countdowns: Vec<MarkerCountdown>,
+    pub labels: Vec<MarkerLabel>,
+    /// active markers around the player. only collected when the closest markers window is open
+    pub nearby: Vec<NearbyMarker>,
+    /// unit of the distance labels and the closest markers list
+    pub distance_unit: DistanceUnit,
+}
+
+impl InfoLayer {
+    /// default taco info range in meters
+    pub const DEFAULT_INFO_RANGE: f32 = 2.0;
+    /// vertical position of the info box as a ratio of screen height (taco shows it a little above the center of the screen)
+    const INFO_BOX_HEIGHT_RATIO: f32 = 0.3;
+
+    pub fn clear(&mut self) {
+        self.infos.clear();
+        self.titles.clear();
+        self.countdowns.clear();
+        self.labels.clear();
+        self.nearby.clear();
+    }
+
+    pub fn gui(&mut self, etx: &egui::Context, joko_renderer: &joko_render::JokoRenderer) {
+        let screen_rect = etx.screen_rect();
+        let screen_size = vec2(screen_rect.width(), screen_rect.height());
+        let painter = etx.layer_painter(LayerId::background());
+        for title in self.titles.iter() {
+            if let Some(pos) = joko_renderer.world_to_screen(title.position, screen_size) {
+                let [r, g, b, a] = title.color;
+                painter.text(
+                    egui::pos2(pos.x, pos.y),
+                    Align2::CENTER_BOTTOM,
+                    title.title.as_str(),
+                    FontId::proportional(16.0),
+                    Color32::from_rgba_unmultiplied(r, g, b, a),
+                );
+            }
+        }
+
+        for countdown in self.countdowns.iter() {
+            let Some(position) = countdown.position else {
+                continue;
+            };
+            if let Some(pos) = joko_renderer.world_to_screen(position, screen_size) {
+                painter.text(
+                    egui::pos2(pos.x, pos.y),
+                    Align2::CENTER_TOP,
+                    format_countdown(countdown.remaining),
+                    FontId::monospace(16.0),
+                    Color32::WHITE,
+                );
+            }
+        }
+
+        for label in self.labels.iter() {
+            if let Some(pos) = joko_renderer.world_to_screen(label.position, screen_size) {
+                let offset = if label.below_countdown { 18.0 } else { 0.0 };
+                painter.text(
+                    egui::pos2(pos.x, pos.y + offset),
+                    Align2::CENTER_TOP,
+                    format_distance(label.distance, self.distance_unit),
+                    FontId::proportional(14.0),
+                    Color32::WHITE.gamma_multiply(label.alpha.clamp(0.0, 1.0)),
+                );
+            }
+        }
+
+        if self.infos.is_empty() {
+            return;
+        }
+        self.infos
+            .sort_unstable_by(|first, second| first.distance.total_cmp(&second.distance));
+        egui::Area::new("marker info")
+            .anchor(
+                Align2::CENTER_TOP,
+                [0.0, screen_size.y * Self::INFO_BOX_HEIGHT_RATIO],
+            )
+            .interactable(false)
+            .order(egui::Order::Background)
+            .show(etx, |ui| {
+                egui::Frame::none()
+                    .fill(Color32::from_black_alpha(160))
+                    .rounding(4.0)
+                    .inner_margin(8.0)
+                    .show(ui, |ui| {
+                        for (index, info) in self.infos.iter().enumerate() {
+                            if index != 0 {
+                                ui.separator();
+                            }
+                            ui.label(
+                                egui::RichText::new(info.info.as_str())
+                                    .color(Color32::WHITE)
+                                    .size(16.0),
+                            );
+                        }
+                    });
+            });
+    }
+
+    /// sorts the nearby markers by distance and keeps the closest `count` markers
+    pub fn keep_closest(&mut self, count: usize) {
+        self.nearby
+            .sort_unstable_by(|first, second| first.distance.total_cmp(&second.distance));
+        self.nearby.truncate(count);
+    }
+
+    /// the closest markers window
+    pub fn closest_markers_gui(&self, etx: &egui::Context, open: &mut bool) {
+        egui::Window::new("Closest Markers")
+            .open(open)
+            .show(etx, |ui| {
+                if self.nearby.is_empty() {
+                    ui.label("no markers nearby");
+                    return;
+                }
+                egui::Grid::new("closest markers")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for marker in self.nearby.iter() {
+                            ui.label(&marker.label);
+                            ui.monospace(format_distance(marker.distance, self.distance_unit));
+                            ui.label(format_bearing(marker.bearing));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+
+    /// lists the countdowns of this frame sorted by the remaining time
+    pub fn timers_ui(&mut self, ui: &mut egui::Ui) {
+        if self.countdowns.is_empty() {
+            ui.label("no active timers");
+            return;
+        }
+        self.countdowns.sort_by_key(|countdown| countdown.remaining);
+        egui::Grid::new("marker timers")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                for countdown in self.countdowns.iter() {
+                    ui.label(&countdown.label);
+                    ui.monospace(format_countdown(countdown.remaining));
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_in_both_units() {
+        assert_eq!(format_distance(123.4, DistanceUnit::Meters), "123 m");
+        assert_eq!(format_distance(10.0, DistanceUnit::GameUnits), "394 in");
+    }
+
+    #[test]
+    fn bearing_relative_to_camera() {
+        let player = Vec3::new(5.0, 0.0, 5.0);
+        // camera looks north (+z)
+        assert_eq!(bearing(Vec3::Z, player, player + Vec3::Z * 10.0), 0.0);
+        assert_eq!(bearing(Vec3::Z, player, player + Vec3::X), 90.0);
+        assert_eq!(bearing(Vec3::Z, player, player - Vec3::X), -90.0);
+        assert_eq!(bearing(Vec3::Z, player, player - Vec3::Z).abs(), 180.0);
+        // height doesn't matter
+        let bearing = bearing(
+            Vec3::new(1.0, -1.0, 0.0),
+            player,
+            player + Vec3::new(1.0, 5.0, 1.0),
+        );
+        assert!((bearing + 45.0).abs() < 0.001);
+    }
+}
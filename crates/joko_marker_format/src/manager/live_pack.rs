@@ -1,25 +1,38 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Instant,
 };
 
 use cap_std::fs_utf8::Dir;
 use egui::{ColorImage, TextureHandle};
-use glam::{vec2, Vec2, Vec3};
+use glam::{vec2, vec3, EulerRot, Quat, Vec2, Vec3};
 use image::EncodableLayout;
 use indexmap::IndexMap;
 use joko_render::billboard::{MarkerObject, MarkerVertex, TrailObject};
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
+use super::{
+    animation::{scroll_texture_coordinates, trail_scroll_offset, BounceTrigger, MarkerAnimation},
+    edge::{EdgeLayer, EdgeMarker},
+    info::{
+        bearing, InfoLayer, MarkerCountdown, MarkerInfo, MarkerLabel, MarkerTitle, NearbyMarker,
+    },
+    map_layer::{MapAttributes, MapLayer, MapMarker, MapTrail},
+    spatial::{Frustum, SpatialGrid},
+    timer::{next_daily_reset, next_map_reset, next_weekly_reset},
+    trail_mesh::{build_trail_mesh, TrailStyle},
+};
 use crate::{
     io::{load_pack_core_from_dir, save_pack_core_to_dir},
-    pack::{Category, CommonAttributes, PackCore, RelativePath},
+    pack::{Behavior, Category, CommonAttributes, Cull, PackCore, RelativePath},
     INCHES_PER_METER,
 };
 use jokolink::MumbleLink;
 use miette::{bail, Context, IntoDiagnostic, Result};
 use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
 
 pub(crate) struct LoadedPack {
     /// The directory inside which the pack data is stored
@@ -34,6 +47,12 @@ pub(crate) struct LoadedPack {
     dirty: Dirty,
     activation_data: ActivationData,
     current_map_data: CurrentMapData,
+    /// name of the mumble link which [Self::current_map_data] belongs to
+    current_link_name: String,
+    /// map data of the other gw2 clients when multiboxing. swapped with the current map data when a different client gets focus
+    other_links_map_data: HashMap<String, CurrentMapData>,
+    /// trails scroll based on the time since this instant
+    animation_start: Instant,
 }
 
 #[derive(Debug, Default, Clone)]
@@ -49,6 +68,8 @@ struct Dirty {
     texture: HashSet<RelativePath>,
     /// whether any tbin needs saving
     tbin: HashSet<RelativePath>,
+    /// whether activation data needs saving
+    activation: bool,
 }
 
 impl Dirty {
@@ -58,6 +79,7 @@ impl Dirty {
             || !self.map_dirty.is_empty()
             || !self.texture.is_empty()
             || !self.tbin.is_empty()
+            || self.activation
     }
 }
 /// This is the activation data per pack
@@ -77,6 +99,38 @@ pub enum ActivationType {
     TimeStamp(time::OffsetDateTime),
     Instance(std::net::IpAddr),
 }
+impl ActivationData {
+    /// records the activation of a marker based on its behavior.
+    /// returns the timestamp when the marker will reappear, if the behavior is timer based.
+    fn activate(
+        &mut self,
+        guid: Uuid,
+        attrs: &CommonAttributes,
+        link: &MumbleLink,
+        now: OffsetDateTime,
+    ) -> Option<OffsetDateTime> {
+        let behavior = attrs.get_behavior().copied()?;
+        let reset_length = attrs.get_reset_length().copied().unwrap_or_default();
+        let wakeup = match behavior {
+            Behavior::ReappearAfterTimer => now + time::Duration::seconds_f32(reset_length),
+            Behavior::ReappearOnDailyReset | Behavior::DailyPerChar => next_daily_reset(now),
+            Behavior::ReappearOnMapReset => next_map_reset(
+                now,
+                attrs.get_reset_offset().copied().unwrap_or_default(),
+                reset_length,
+            ),
+            Behavior::WeeklyReset => next_weekly_reset(now),
+            _ => return None,
+        };
+        let activations = if behavior == Behavior::DailyPerChar {
+            self.character.entry(link.name.clone()).or_default()
+        } else {
+            &mut self.global
+        };
+        activations.insert(guid, ActivationType::TimeStamp(wakeup));
+        Some(wakeup)
+    }
+}
 impl LoadedPack {
     const CORE_PACK_DIR_NAME: &str = "core";
     const CATEGORY_SELECTION_FILE_NAME: &str = "cats.json";
@@ -92,6 +146,9 @@ impl LoadedPack {
                 ..Default::default()
             },
             current_map_data: Default::default(),
+            current_link_name: Default::default(),
+            other_links_map_data: Default::default(),
+            animation_start: Instant::now(),
             dir,
             activation_data: Default::default(),
         }
@@ -177,16 +234,23 @@ impl LoadedPack {
             cats_selection,
             dirty: Default::default(),
             current_map_data: Default::default(),
+            current_link_name: Default::default(),
+            other_links_map_data: Default::default(),
+            animation_start: Instant::now(),
             activation_data,
         })
     }
+    #[allow(clippy::too_many_arguments)]
     pub fn tick(
         &mut self,
         etx: &egui::Context,
-        _timestamp: f64,
+        now: OffsetDateTime,
         joko_renderer: &mut joko_render::JokoRenderer,
         link: &Option<Arc<MumbleLink>>,
         default_tex_id: &TextureHandle,
+        info_layer: &mut InfoLayer,
+        edge_layer: &mut EdgeLayer,
+        max_marker_distance: f32,
     ) {
         let categories_changed = self.dirty.cats_selection;
         if self.dirty.is_dirty() {
@@ -201,28 +265,280 @@ impl LoadedPack {
             Some(link) => link,
             None => return,
         };
+        if categories_changed {
+            // other clients will rebuild their map data when they get focus again
+            self.other_links_map_data.clear();
+        }
+        if self.current_link_name != link.link_name {
+            self.switch_link(&link.link_name);
+        }
 
         if self.current_map_data.map_id != link.map_id || categories_changed {
-            self.on_map_changed(etx, link, default_tex_id);
+            self.on_map_changed(etx, link, default_tex_id, now);
         }
+        // the world map covers the whole screen. markers are drawn on the map by the map layer instead.
+        // but timers and activations must keep going
+        let map_open = link.is_map_open();
         let z_near = joko_renderer.get_z_near();
-        for marker in self.current_map_data.active_markers.values() {
-            if let Some(mo) = marker.get_vertices_and_texture(link, z_near) {
+        // taco's action key. only works when jokolay has keyboard focus. and not while the player is typing in chat
+        let action_key_pressed =
+            !link.textbox_focused() && etx.input(|i| i.key_pressed(egui::Key::F));
+        let frustum = Frustum::from_view_proj(joko_renderer.view_proj);
+        // last frame's list is needed to find the markers that left the query
+        let previous_visible_markers = std::mem::take(&mut self.current_map_data.visible_markers);
+        let mut visible_markers = std::mem::take(&mut self.current_map_data.spare_visible_markers);
+        visible_markers.clear();
+        self.current_map_data.spatial_index.query_frustum(
+            &frustum,
+            link.cam_pos,
+            max_marker_distance,
+            &mut visible_markers,
+        );
+        // markers near the player must still be activated and show their info, even if they are behind the camera
+        self.current_map_data.spatial_index.query_sphere(
+            link.player_pos,
+            self.current_map_data.interaction_range,
+            &mut visible_markers,
+        );
+        visible_markers.sort_unstable();
+        visible_markers.dedup();
+        // the sphere query includes every marker within its trigger range, so the ones that are not queried anymore are out of range.
+        // otherwise, they would not trigger again when the player comes back
+        for index in previous_visible_markers.iter() {
+            if visible_markers.binary_search(index).is_err() {
+                if let Some(marker) = self.current_map_data.active_markers.get_mut(index) {
+                    marker.in_trigger_range = false;
+                }
+            }
+        }
+        self.current_map_data.spare_visible_markers = previous_visible_markers;
+        // every timer goes into the timers list, even if the marker is behind the camera or far away.
+        // the drawn ones also get a position in the world below. marker index -> index in countdowns
+        let mut countdowns = HashMap::new();
+        for index in self.current_map_data.timer_markers.iter() {
+            let Some(marker) = self.current_map_data.active_markers.get(index) else {
+                continue;
+            };
+            if let Some(remaining) = marker.countdown(now) {
+                countdowns.insert(*index, info_layer.countdowns.len());
+                info_layer.countdowns.push(MarkerCountdown {
+                    position: None,
+                    label: marker.label(),
+                    remaining,
+                });
+            }
+        }
+        for index in visible_markers.iter() {
+            let Some(marker) = self.current_map_data.active_markers.get_mut(index) else {
+                continue;
+            };
+            if let Some(wakeup) = marker.wakeup {
+                if wakeup > now {
+                    // sleeping marker
+                    if let Some(&countdown) = countdowns.get(index) {
+                        let height_offset = marker
+                            .attrs
+                            .get_height_offset()
+                            .copied()
+                            .unwrap_or(ActiveMarker::DEFAULT_HEIGHT_OFFSET);
+                        info_layer.countdowns[countdown].position =
+                            Some(marker.pos + Vec3::Y * height_offset);
+                    }
+                    continue;
+                }
+                marker.wakeup = None;
+            }
+            if marker.update_activation(link, action_key_pressed) {
+                if let Some(copy) = marker.attrs.get_copy() {
+                    etx.output_mut(|o| o.copied_text = copy.to_string());
+                    if let Some(copy_message) = marker.attrs.get_copy_message() {
+                        info!(notify = 4.0, "{copy_message}");
+                    }
+                }
+                if let Some(wakeup) =
+                    self.activation_data
+                        .activate(marker.guid, &marker.attrs, link, now)
+                {
+                    marker.wakeup = Some(wakeup);
+                    self.dirty.activation = true;
+                    continue;
+                }
+            }
+            marker
+                .animation
+                .tick(&marker.attrs, marker.in_trigger_range, now);
+            let drawn = if map_open {
+                None
+            } else {
+                marker.get_vertices_and_texture(link, z_near)
+            };
+            if let Some(mo) = drawn {
+                // event timers show the time until next spawn even when they are visible
+                let shows_countdown = match countdowns.get(index) {
+                    Some(&countdown) => {
+                        // midpoint of bottom left and bottom right vertices
+                        info_layer.countdowns[countdown].position =
+                            Some((mo.vertices[1].position + mo.vertices[2].position) / 2.0);
+                        true
+                    }
+                    None => false,
+                };
+                if marker.options.distance_label {
+                    info_layer.labels.push(MarkerLabel {
+                        position: (mo.vertices[1].position + mo.vertices[2].position) / 2.0,
+                        distance: marker.pos.distance(link.player_pos),
+                        alpha: mo.vertices[0].alpha,
+                        below_countdown: shows_countdown,
+                    });
+                }
+                if let Some(title) = marker.attrs.get_title() {
+                    info_layer.titles.push(MarkerTitle {
+                        // midpoint of top left and top right vertices
+                        position: (mo.vertices[0].position + mo.vertices[4].position) / 2.0,
+                        title: title.clone(),
+                        color: marker.attrs.get_title_color().copied().unwrap_or([255; 4]),
+                    });
+                }
                 joko_renderer.add_billboard(mo);
             }
+            if let Some(info) = marker.attrs.get_info() {
+                let info_range = marker
+                    .attrs
+                    .get_info_range()
+                    .copied()
+                    .unwrap_or(InfoLayer::DEFAULT_INFO_RANGE);
+                let distance = marker.pos.distance(link.player_pos);
+                if distance <= info_range {
+                    info_layer.infos.push(MarkerInfo {
+                        distance,
+                        info: info.clone(),
+                    });
+                }
+            }
+        }
+        self.current_map_data.visible_markers = visible_markers;
+        if map_open {
+            return;
+        }
+        for index in self.current_map_data.herded_markers.iter() {
+            let Some(marker) = self.current_map_data.active_markers.get(index) else {
+                continue;
+            };
+            if marker.wakeup.is_some_and(|wakeup| wakeup > now)
+                || marker.pos.distance(link.cam_pos) > max_marker_distance
+            {
+                continue;
+            }
+            let Some(fade) = marker.visibility(link) else {
+                continue;
+            };
+            let height_offset = marker
+                .attrs
+                .get_height_offset()
+                .copied()
+                .unwrap_or(ActiveMarker::DEFAULT_HEIGHT_OFFSET);
+            let alpha = marker.attrs.get_alpha().copied().unwrap_or(1.0) * fade;
+            edge_layer.markers.push(EdgeMarker {
+                position: marker.pos + Vec3::Y * height_offset,
+                texture: marker.texture_handle.id(),
+                tint: egui::Color32::WHITE.gamma_multiply(alpha),
+            });
         }
         for trail in self.current_map_data.active_trails.values() {
+            if !trail.in_game_visibility {
+                continue;
+            }
+            let vertices = if trail.anim_speed != 0.0 {
+                scroll_texture_coordinates(
+                    &trail.trail_object.vertices,
+                    trail_scroll_offset(self.animation_start.elapsed(), trail.anim_speed),
+                )
+                .into()
+            } else {
+                trail.trail_object.vertices.clone()
+            };
             joko_renderer.add_trail(TrailObject {
-                vertices: trail.trail_object.vertices.clone(),
+                vertices,
                 texture: trail.trail_object.texture,
             });
         }
     }
+    /// collects the active (and not sleeping) markers within `max_distance` of the player for the closest markers list
+    pub fn nearby_markers(
+        &self,
+        link: &MumbleLink,
+        now: OffsetDateTime,
+        max_distance: f32,
+        nearby: &mut Vec<NearbyMarker>,
+    ) {
+        let mut indices = vec![];
+        self.current_map_data.spatial_index.query_sphere(
+            link.player_pos,
+            max_distance,
+            &mut indices,
+        );
+        for index in indices {
+            let Some(marker) = self.current_map_data.active_markers.get(&index) else {
+                continue;
+            };
+            if marker.wakeup.is_some_and(|wakeup| wakeup > now)
+                || !marker.attrs.get_in_game_visibility().unwrap_or(true)
+            {
+                continue;
+            }
+            nearby.push(NearbyMarker {
+                label: marker.label(),
+                distance: marker.pos.distance(link.player_pos),
+                bearing: bearing(link.f_camera_front, link.player_pos, marker.pos),
+            });
+        }
+    }
+    /// collects the active (and not sleeping) markers and trails which are visible on the minimap/world map
+    pub fn map_tick(&self, now: OffsetDateTime, map_layer: &mut MapLayer) {
+        for marker in self.current_map_data.active_markers.values() {
+            if marker.wakeup.is_some_and(|wakeup| wakeup > now) {
+                continue;
+            }
+            if let Some(attrs) = marker.map_attrs {
+                let alpha = marker.attrs.get_alpha().copied().unwrap_or(1.0);
+                map_layer.markers.push(MapMarker {
+                    position: marker.pos,
+                    texture: marker.texture_handle.id(),
+                    tint: egui::Color32::WHITE.gamma_multiply(alpha),
+                    attrs,
+                });
+            }
+        }
+        for trail in self.current_map_data.active_trails.values() {
+            if let Some(attrs) = trail.map_attrs {
+                map_layer.trails.push(MapTrail {
+                    nodes: trail.nodes.clone(),
+                    color: trail.map_color,
+                    attrs,
+                });
+            }
+        }
+    }
+    /// keeps the map data of the previous link around and restores the map data of the new link (if any)
+    fn switch_link(&mut self, link_name: &str) {
+        let map_data = self
+            .other_links_map_data
+            .remove(link_name)
+            .unwrap_or_default();
+        let previous_map_data = std::mem::replace(&mut self.current_map_data, map_data);
+        let previous_link_name =
+            std::mem::replace(&mut self.current_link_name, link_name.to_string());
+        if previous_map_data.map_id != 0 {
+            self.other_links_map_data
+                .insert(previous_link_name, previous_map_data);
+        }
+    }
     fn on_map_changed(
         &mut self,
         etx: &egui::Context,
         link: &MumbleLink,
         default_tex_id: &TextureHandle,
+        now: OffsetDateTime,
     ) {
         info!(
             self.current_map_data.map_id,
@@ -240,6 +556,7 @@ impl LoadedPack {
             &mut enabled_cats_list,
             "",
             &Default::default(),
+            &Default::default(),
         );
         for (index, marker) in self
             .core
@@ -250,13 +567,29 @@ impl LoadedPack {
             .iter()
             .enumerate()
         {
-            if let Some(category_attributes) = enabled_cats_list.get(&marker.category) {
+            if let Some((category_attributes, category_options)) =
+                enabled_cats_list.get(&marker.category)
+            {
                 let mut attrs = marker.attrs.clone();
                 attrs.inherit_if_attr_none(category_attributes);
                 let key = &marker.guid;
+                let mut wakeup = None;
                 if let Some(behavior) = attrs.get_behavior() {
-                    use crate::pack::Behavior;
-                    if match behavior {
+                    let activation = match behavior {
+                        Behavior::DailyPerChar | Behavior::OncePerInstancePerChar => self
+                            .activation_data
+                            .character
+                            .get(&link.name)
+                            .and_then(|a| a.get(key)),
+                        _ => self.activation_data.global.get(key),
+                    };
+                    if let Some(ActivationType::TimeStamp(timestamp)) = activation {
+                        // if the timer is still running, we keep the marker sleeping until the timestamp.
+                        // otherwise, the marker is visible again.
+                        if *timestamp > now {
+                            wakeup = Some(*timestamp);
+                        }
+                    } else if match behavior {
                         Behavior::AlwaysVisible => false,
                         Behavior::ReappearOnMapChange
                         | Behavior::ReappearOnDailyReset
@@ -332,20 +665,51 @@ impl LoadedPack {
 
                 let max_pixel_size = attrs.get_max_size().copied().unwrap_or(2048.0); // default taco max size
                 let min_pixel_size = attrs.get_min_size().copied().unwrap_or(5.0); // default taco min size
+                let map_attrs =
+                    MapAttributes::new(&attrs, MapAttributes::DEFAULT_MARKER_DISPLAY_SIZE);
                 self.current_map_data.active_markers.insert(
                     index,
                     ActiveMarker {
                         texture_id,
-                        _texture: th.clone(),
+                        texture_handle: th.clone(),
                         attrs,
                         pos: marker.position,
                         max_pixel_size,
                         min_pixel_size,
+                        in_trigger_range: false,
+                        guid: marker.guid,
+                        category: marker.category.clone(),
+                        wakeup,
+                        animation: Default::default(),
+                        map_attrs,
+                        options: *category_options,
                     },
                 );
             }
         }
 
+        let active_markers = &self.current_map_data.active_markers;
+        self.current_map_data.spatial_index = SpatialGrid::with_radii(
+            SpatialGrid::DEFAULT_CELL_SIZE,
+            active_markers
+                .iter()
+                .map(|(index, marker)| (*index, marker.pos, marker.cull_radius())),
+        );
+        self.current_map_data.interaction_range = active_markers
+            .values()
+            .map(|marker| marker.interaction_range())
+            .fold(0.0, f32::max);
+        self.current_map_data.timer_markers = active_markers
+            .iter()
+            .filter(|(_, marker)| marker.attrs.get_has_countdown().unwrap_or_default())
+            .map(|(index, _)| *index)
+            .collect();
+        self.current_map_data.herded_markers = active_markers
+            .iter()
+            .filter(|(_, marker)| marker.options.edge_herd)
+            .map(|(index, _)| *index)
+            .collect();
+
         for (index, trail) in self
             .core
             .maps
@@ -355,7 +719,7 @@ impl LoadedPack {
             .iter()
             .enumerate()
         {
-            if let Some(category_attributes) = enabled_cats_list.get(&trail.category) {
+            if let Some((category_attributes, _)) = enabled_cats_list.get(&trail.category) {
                 let mut common_attributes = trail.props.clone();
                 common_attributes.inherit_if_attr_none(category_attributes);
                 if let Some(tex_path) = common_attributes.get_texture() {
@@ -439,6 +803,21 @@ impl LoadedPack {
             .open_dir(Self::CORE_PACK_DIR_NAME)
             .into_diagnostic()
             .wrap_err("failed to open core pack directory")?;
+        if std::mem::take(&mut self.dirty.activation) || self.dirty.all {
+            match serde_json::to_string_pretty(&self.activation_data) {
+                Ok(ad_json) => match self.dir.write(Self::ACTIVATION_DATA_FILE_NAME, ad_json) {
+                    Ok(_) => {
+                        debug!("wrote activation data to disk");
+                    }
+                    Err(e) => {
+                        debug!(?e, "failed to write activation data to disk");
+                    }
+                },
+                Err(e) => {
+                    error!(?e, "failed to serialize activation data");
+                }
+            }
+        }
         save_pack_core_to_dir(
             &self.core,
             &core_dir,
@@ -463,6 +842,18 @@ pub(crate) struct CurrentMapData {
     pub active_markers: IndexMap<usize, ActiveMarker>,
     /// The key is the position/index of this trail in the map trails. same as markers
     pub active_trails: IndexMap<usize, ActiveTrail>,
+    /// spatial index of the active markers, so that we only need to check the markers around the player/camera every frame
+    pub spatial_index: SpatialGrid,
+    /// the biggest trigger/info range among the active markers. markers within this distance of the player are always checked
+    pub interaction_range: f32,
+    /// indices of the markers that were queried this frame, sorted. kept around to reuse the allocation
+    pub visible_markers: Vec<usize>,
+    /// last frame's allocation of [Self::visible_markers]
+    pub spare_visible_markers: Vec<usize>,
+    /// indices of the markers whose category has edge herding enabled. these are checked every frame, even if they are not visible
+    pub herded_markers: Vec<usize>,
+    /// indices of the markers with `hasCountdown`. their countdowns are listed in the timers panel even when they are not visible
+    pub timer_markers: Vec<usize>,
 }
 
 /*
@@ -473,6 +864,16 @@ pub(crate) struct CurrentMapData {
 pub struct ActiveTrail {
     pub trail_object: TrailObject,
     pub texture_handle: TextureHandle,
+    /// false if the trail must only be shown on the map layers
+    pub in_game_visibility: bool,
+    /// how fast the texture scrolls along the trail. 0.0 means the trail is not animated
+    pub anim_speed: f32,
+    /// the trail nodes, used to draw the trail on the minimap/world map
+    pub nodes: Arc<[Vec3]>,
+    /// None if the trail is not visible on any map
+    pub map_attrs: Option<MapAttributes>,
+    /// color of the trail on the minimap/world map
+    pub map_color: egui::Color32,
 }
 /// This is an active marker.
 /// It stores all the info that we need to scan every frame
@@ -480,7 +881,7 @@ pub(crate) struct ActiveMarker {
     /// texture id from managed textures
     pub texture_id: u64,
     /// owned texture handle to keep it alive
-    pub _texture: TextureHandle,
+    pub texture_handle: TextureHandle,
     /// position
     pub pos: Vec3,
     /// billboard must not be bigger than this size in pixels
@@ -488,12 +889,61 @@ pub(crate) struct ActiveMarker {
     /// billboard must not be smaller than this size in pixels
     pub min_pixel_size: f32,
     pub attrs: CommonAttributes,
+    /// whether the player was within the trigger range of this marker in the previous frame
+    pub in_trigger_range: bool,
+    /// guid of the marker. used as the key for activation data
+    pub guid: Uuid,
+    /// full name of the category of this marker
+    pub category: String,
+    /// if the marker was activated and is sleeping, this is when it will reappear
+    pub wakeup: Option<OffsetDateTime>,
+    /// temporary animation state like bounce
+    pub animation: MarkerAnimation,
+    /// None if the marker is not visible on any map
+    pub map_attrs: Option<MapAttributes>,
+    /// jokolay specific display options of the category of this marker
+    pub options: CategoryOptions,
+}
+/// Display options of a category which are not part of the pack, but chosen by the user.
+/// The options of a category also apply to all of its sub categories.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy)]
+pub(crate) struct CategoryOptions {
+    /// keep the markers on the screen. off-screen markers are pinned to the edge of the screen with an arrow pointing towards them
+    #[serde(default)]
+    pub edge_herd: bool,
+    /// show the distance from the player below the markers
+    #[serde(default)]
+    pub distance_label: bool,
+}
+impl CategoryOptions {
+    fn inherit(&mut self, parent: &Self) {
+        self.edge_herd |= parent.edge_herd;
+        self.distance_label |= parent.distance_label;
+    }
+    fn ui(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        if ui
+            .checkbox(&mut self.edge_herd, "pin to screen edge")
+            .on_hover_text("shows off-screen markers of this category at the edge of the screen")
+            .changed()
+        {
+            *changed = true;
+        }
+        if ui
+            .checkbox(&mut self.distance_label, "show distance")
+            .on_hover_text("shows the distance from the player below the markers of this category")
+            .changed()
+        {
+            *changed = true;
+        }
+    }
 }
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 struct CategorySelection {
     pub selected: bool,
     pub display_name: String,
     pub children: HashMap<String, CategorySelection>,
+    #[serde(default)]
+    pub options: CategoryOptions,
 }
 
 impl CategorySelection {
@@ -505,9 +955,10 @@ impl CategorySelection {
     fn recursive_get_full_names(
         selection: &HashMap<String, CategorySelection>,
         cats: &IndexMap<String, Category>,
-        list: &mut HashMap<String, CommonAttributes>,
+        list: &mut HashMap<String, (CommonAttributes, CategoryOptions)>,
         parent_name: &str,
         parent_common_attributes: &CommonAttributes,
+        parent_options: &CategoryOptions,
     ) {
         for (name, cat) in cats {
             if let Some(selected_cat) = selection.get(name) {
@@ -521,14 +972,17 @@ impl CategorySelection {
                 };
                 let mut common_attributes = cat.props.clone();
                 common_attributes.inherit_if_attr_none(parent_common_attributes);
+                let mut options = selected_cat.options;
+                options.inherit(parent_options);
                 Self::recursive_get_full_names(
                     &selected_cat.children,
                     &cat.children,
                     list,
                     &full_name,
                     &common_attributes,
+                    &options,
                 );
-                list.insert(full_name, common_attributes);
+                list.insert(full_name, (common_attributes, options));
             }
         }
     }
@@ -554,28 +1008,171 @@ impl CategorySelection {
                     if ui.checkbox(&mut cat.selected, "").changed() {
                         *changed = true;
                     }
-                    if !cat.children.is_empty() {
+                    let response = if !cat.children.is_empty() {
                         ui.menu_button(&cat.display_name, |ui: &mut egui::Ui| {
                             Self::recursive_selection_ui(&mut cat.children, ui, changed);
-                        });
+                        })
+                        .response
                     } else {
-                        ui.label(&cat.display_name);
-                    }
+                        ui.label(&cat.display_name)
+                    };
+                    // right click for the display options
+                    response.context_menu(|ui| {
+                        cat.options.ui(ui, changed);
+                    });
                 });
             }
         });
     }
 }
 
-pub const _BILLBOARD_MAX_VISIBILITY_DISTANCE: f32 = 10000.0;
+/// default max distance from the camera within which markers are drawn. in meters
+pub const DEFAULT_MAX_MARKER_DISTANCE: f32 = 1000.0;
 
 impl ActiveMarker {
+    /// default taco trigger range in meters
+    pub const DEFAULT_TRIGGER_RANGE: f32 = 2.0;
+    /// default taco height offset in meters
+    pub const DEFAULT_HEIGHT_OFFSET: f32 = 1.5;
+    /// the distance around the position of this marker within which its quad (with height offset and bounce) is drawn.
+    /// used for frustum culling. billboards clamped to `minSize` pixels can be a little bigger when far away, but that's fine at the edges of the screen.
+    pub fn cull_radius(&self) -> f32 {
+        let height_offset = self
+            .attrs
+            .get_height_offset()
+            .copied()
+            .unwrap_or(Self::DEFAULT_HEIGHT_OFFSET);
+        let bounce_height = if self
+            .attrs
+            .get_bounce()
+            .and_then(|bounce| BounceTrigger::from_attr(bounce))
+            .is_some()
+        {
+            self.attrs
+                .get_bounce_height()
+                .copied()
+                .unwrap_or(MarkerAnimation::DEFAULT_BOUNCE_HEIGHT)
+        } else {
+            0.0
+        };
+        // the quad is `iconSize` meters from its center to the sides, so the corners are a little farther
+        let icon_size = self.attrs.get_icon_size().copied().unwrap_or(1.0);
+        height_offset.abs() + bounce_height.abs() + icon_size.abs() * std::f32::consts::SQRT_2
+    }
+    /// time until this marker wakes up or, for event timers, until the next spawn. None if the marker doesn't show a countdown
+    pub fn countdown(&self, now: OffsetDateTime) -> Option<time::Duration> {
+        if !self.attrs.get_has_countdown().unwrap_or_default() {
+            return None;
+        }
+        match self.wakeup {
+            Some(wakeup) if wakeup > now => Some(wakeup - now),
+            _ if self.attrs.get_behavior() == Some(&Behavior::ReappearOnMapReset) => {
+                let next_spawn = next_map_reset(
+                    now,
+                    self.attrs.get_reset_offset().copied().unwrap_or_default(),
+                    self.attrs.get_reset_length().copied().unwrap_or_default(),
+                );
+                Some(next_spawn - now)
+            }
+            _ => None,
+        }
+    }
+    /// the distance within which the player can interact with this marker (trigger or show info)
+    pub fn interaction_range(&self) -> f32 {
+        let trigger_range = self
+            .attrs
+            .get_trigger_range()
+            .copied()
+            .unwrap_or(Self::DEFAULT_TRIGGER_RANGE);
+        let info_range = if self.attrs.get_info().is_some() {
+            self.attrs
+                .get_info_range()
+                .copied()
+                .unwrap_or(InfoLayer::DEFAULT_INFO_RANGE)
+        } else {
+            0.0
+        };
+        trigger_range.max(info_range)
+    }
+    /// name of the marker to show in the ui. tipName or title if they exist. otherwise the category of the marker
+    pub fn label(&self) -> String {
+        self.attrs
+            .get_tip_name()
+            .or(self.attrs.get_title())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| self.category.clone())
+    }
+    /// Checks if the marker is activated this frame.
+    /// A marker is activated when the player is within its trigger range AND
+    /// 1. `autoTrigger` is set and the player *just* entered the trigger range
+    /// 2. or the action key was pressed this frame
+    pub fn update_activation(&mut self, link: &MumbleLink, action_key_pressed: bool) -> bool {
+        let trigger_range = self
+            .attrs
+            .get_trigger_range()
+            .copied()
+            .unwrap_or(Self::DEFAULT_TRIGGER_RANGE);
+        let in_range = self.pos.distance(link.player_pos) <= trigger_range;
+        let just_entered = in_range && !self.in_trigger_range;
+        self.in_trigger_range = in_range;
+        if !in_range {
+            return false;
+        }
+        action_key_pressed || (just_entered && self.attrs.get_auto_trigger().unwrap_or_default())
+    }
+    /// The rotation of a fixed orientation (non-billboard) marker. None if the marker doesn't set any rotation and is a regular billboard.
+    /// `rotate` sets all three euler angles (in degrees) and `rotate-x/y/z` override the individual angles.
+    pub fn rotation(&self) -> Option<Quat> {
+        let rotate = self.attrs.get_rotate().copied();
+        let rotate_x = self.attrs.get_rotate_x().copied();
+        let rotate_y = self.attrs.get_rotate_y().copied();
+        let rotate_z = self.attrs.get_rotate_z().copied();
+        if rotate.is_none() && rotate_x.is_none() && rotate_y.is_none() && rotate_z.is_none() {
+            return None;
+        }
+        let rotate = rotate.unwrap_or_default();
+        let degrees = vec3(
+            rotate_x.unwrap_or(rotate.x),
+            rotate_y.unwrap_or(rotate.y),
+            rotate_z.unwrap_or(rotate.z),
+        );
+        Some(Quat::from_euler(
+            EulerRot::XYZ,
+            degrees.x.to_radians(),
+            degrees.y.to_radians(),
+            degrees.z.to_radians(),
+        ))
+    }
+    /// The in game visibility filters (ingame visibility, mount, fadeNear/fadeFar) of this marker.
+    /// returns None if the marker is hidden. Otherwise, the alpha multiplier due to fading
+    pub fn visibility(&self, link: &MumbleLink) -> Option<f32> {
+        let attrs = &self.attrs;
+        if !attrs.get_in_game_visibility().unwrap_or(true) {
+            return None;
+        }
+        if let Some(mounts) = attrs.get_mount() {
+            if !link.mount.is_some_and(|current| mounts.contains(current)) {
+                return None;
+            }
+        }
+        // fading is done here instead of the shader, so that we can skip the faded out markers
+        let fade = if attrs.get_can_fade().unwrap_or(true) {
+            distance_fade(
+                self.pos.distance(link.player_pos),
+                attrs.get_fade_near().copied().unwrap_or(-1.0) / INCHES_PER_METER,
+                attrs.get_fade_far().copied().unwrap_or(-1.0) / INCHES_PER_METER,
+            )
+        } else {
+            1.0
+        };
+        (fade > 0.0).then_some(fade)
+    }
+    /// returns None if the marker is not visible in the 3D world this frame
     pub fn get_vertices_and_texture(&self, link: &MumbleLink, z_near: f32) -> Option<MarkerObject> {
         let Self {
             texture_id,
             pos,
             attrs,
-            _texture,
             max_pixel_size,
             min_pixel_size,
             ..
@@ -584,25 +1181,17 @@ impl ActiveMarker {
         // let height = *height;
         let texture_id = *texture_id;
         let pos = *pos;
-        // filters
-        if let Some(mounts) = attrs.get_mount() {
-            if let Some(current) = link.mount {
-                if !mounts.contains(current) {
-                    return None;
-                }
-            } else {
-                return None;
-            }
-        }
-        let height_offset = attrs.get_height_offset().copied().unwrap_or(1.5); // default taco height offset
-        let fade_near = attrs.get_fade_near().copied().unwrap_or(-1.0) / INCHES_PER_METER;
-        let fade_far = attrs.get_fade_far().copied().unwrap_or(-1.0) / INCHES_PER_METER;
+        let fade = self.visibility(link)?;
+        let height_offset = attrs
+            .get_height_offset()
+            .copied()
+            .unwrap_or(Self::DEFAULT_HEIGHT_OFFSET);
         let icon_size = attrs.get_icon_size().copied().unwrap_or(1.0);
         let player_distance = pos.distance(link.player_pos);
         let camera_distance = pos.distance(link.cam_pos);
-        let fade_near_far = Vec2::new(fade_near, fade_far);
+        let fade_near_far = Vec2::new(-1.0, -1.0);
 
-        let alpha = attrs.get_alpha().copied().unwrap_or(1.0);
+        let alpha = attrs.get_alpha().copied().unwrap_or(1.0) * fade;
         let color = attrs.get_color().copied().unwrap_or_default();
         /*
            1. we need to filter the markers
@@ -623,12 +1212,47 @@ impl ActiveMarker {
         mount
         specialization
         */
-        if fade_far > 0.0 && player_distance > fade_far {
-            return None;
-        }
         // markers are 1 meter in width/height by default
         let mut pos = pos;
-        pos.y += height_offset;
+        pos.y += height_offset + self.animation.bounce_height;
+        let vertex = |position: Vec3, texture_coordinates: Vec2| MarkerVertex {
+            position,
+            texture_coordinates,
+            alpha,
+            color,
+            fade_near_far,
+        };
+        if let Some(rotation) = self.rotation() {
+            let (corners, normal) = fixed_quad_corners(
+                pos,
+                icon_size,
+                rotation,
+                attrs.get_is_wall().unwrap_or_default(),
+            );
+            let facing_camera = normal.dot(link.cam_pos - pos) > 0.0;
+            match attrs.get_cull().copied().unwrap_or_default() {
+                Cull::ClockWise if facing_camera => return None,
+                Cull::CounterClockWise if !facing_camera => return None,
+                _ => {}
+            }
+            let [top_left, bottom_left, bottom_right, top_right] = corners;
+            let top_left = vertex(top_left, vec2(0.0, 0.0));
+            let bottom_left = vertex(bottom_left, vec2(0.0, 1.0));
+            let bottom_right = vertex(bottom_right, vec2(1.0, 1.0));
+            let top_right = vertex(top_right, vec2(1.0, 0.0));
+            return Some(MarkerObject {
+                vertices: [
+                    top_left,
+                    bottom_left,
+                    bottom_right,
+                    bottom_right,
+                    top_right,
+                    top_left,
+                ],
+                texture: texture_id,
+                distance: player_distance,
+            });
+        }
         let direction_to_marker = link.cam_pos - pos;
         let direction_to_side = direction_to_marker.normalize().cross(Vec3::Y);
 
@@ -661,35 +1285,22 @@ impl ActiveMarker {
         // But, i will ignore that as that makes markers too small
         let x_offset = far_offset;
         let y_offset = x_offset; // seems all markers are squares
-        let bottom_left = MarkerVertex {
-            position: (pos - (direction_to_side * x_offset) - (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(0.0, 1.0),
-            alpha,
-            color,
-            fade_near_far,
-        };
-
-        let top_left = MarkerVertex {
-            position: (pos - (direction_to_side * x_offset) + (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(0.0, 0.0),
-            alpha,
-            color,
-            fade_near_far,
-        };
-        let top_right = MarkerVertex {
-            position: (pos + (direction_to_side * x_offset) + (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(1.0, 0.0),
-            alpha,
-            color,
-            fade_near_far,
-        };
-        let bottom_right = MarkerVertex {
-            position: (pos + (direction_to_side * x_offset) - (Vec3::Y * y_offset)),
-            texture_coordinates: vec2(1.0, 1.0),
-            alpha,
-            color,
-            fade_near_far,
-        };
+        let bottom_left = vertex(
+            pos - (direction_to_side * x_offset) - (Vec3::Y * y_offset),
+            vec2(0.0, 1.0),
+        );
+        let top_left = vertex(
+            pos - (direction_to_side * x_offset) + (Vec3::Y * y_offset),
+            vec2(0.0, 0.0),
+        );
+        let top_right = vertex(
+            pos + (direction_to_side * x_offset) + (Vec3::Y * y_offset),
+            vec2(1.0, 0.0),
+        );
+        let bottom_right = vertex(
+            pos + (direction_to_side * x_offset) - (Vec3::Y * y_offset),
+            vec2(1.0, 1.0),
+        );
         let vertices = [
             top_left,
             bottom_left,
@@ -706,6 +1317,50 @@ impl ActiveMarker {
     }
 }
 
+/// The alpha multiplier of a marker at `distance` from the player. (all values are in meters)
+/// Markers are fully visible until `fade_near` and then fade out linearly until they are invisible at `fade_far`.
+/// A negative `fade_near` or `fade_far` disables that limit.
+pub(crate) fn distance_fade(distance: f32, fade_near: f32, fade_far: f32) -> f32 {
+    if fade_far > 0.0 && distance >= fade_far {
+        return 0.0;
+    }
+    if fade_near > 0.0 && distance > fade_near && fade_far > fade_near {
+        return 1.0 - (distance - fade_near) / (fade_far - fade_near);
+    }
+    1.0
+}
+
+/// The corners (top left, bottom left, bottom right, top right) of a fixed orientation quad and the normal of its front face.
+/// Before rotation, the quad lies flat on the ground facing up (top edge towards +Z), or if `is_wall` is true, stands upright facing -Z.
+/// Like taco (directx), clockwise triangles are the front faces. So `cull="Clockwise"` hides the quad when the camera is on the side of the normal,
+/// and `cull="CounterClockwise"` hides its back face.
+/// `half_size` is the distance from the center to the edges of the quad. markers pass their `iconSize` here,
+/// which is the same as the side offset of billboards before the min/max pixel size clamping. so, a rotated marker is 2 * iconSize meters wide too.
+pub(crate) fn fixed_quad_corners(
+    center: Vec3,
+    half_size: f32,
+    rotation: Quat,
+    is_wall: bool,
+) -> ([Vec3; 4], Vec3) {
+    let (up, normal) = if is_wall {
+        (Vec3::Y, Vec3::NEG_Z)
+    } else {
+        (Vec3::Z, Vec3::Y)
+    };
+    let right = Vec3::X * half_size;
+    let up = up * half_size;
+    let corner = |local: Vec3| center + rotation * local;
+    (
+        [
+            corner(up - right),
+            corner(-up - right),
+            corner(-up + right),
+            corner(up + right),
+        ],
+        rotation * normal,
+    )
+}
+
 impl ActiveTrail {
     fn get_vertices_and_texture(
         attrs: &CommonAttributes,
@@ -717,71 +1372,29 @@ impl ActiveTrail {
             return None;
         }
         let alpha = attrs.get_alpha().copied().unwrap_or(1.0);
-        let fade_near = attrs.get_fade_near().copied().unwrap_or(-1.0) / INCHES_PER_METER;
-        let fade_far = attrs.get_fade_far().copied().unwrap_or(-1.0) / INCHES_PER_METER;
-        let fade_near_far = Vec2::new(fade_near, fade_far);
+        // trails are faded per vertex in the shader
+        let fade_near_far = if attrs.get_can_fade().unwrap_or(true) {
+            Vec2::new(
+                attrs.get_fade_near().copied().unwrap_or(-1.0),
+                attrs.get_fade_far().copied().unwrap_or(-1.0),
+            ) / INCHES_PER_METER
+        } else {
+            Vec2::new(-1.0, -1.0)
+        };
         let color = attrs.get_color().copied().unwrap_or([0u8; 4]);
-        // default taco width
-        let horizontal_offset = 20.0 / INCHES_PER_METER;
-        // scale it trail scale
-        let horizontal_offset = horizontal_offset * attrs.get_trail_scale().copied().unwrap_or(1.0);
-        let height = horizontal_offset * 2.0;
-
-        let mut vertices = vec![];
-        // trail mesh is split by separating different parts with a [0, 0, 0]
-        // we will call each separate trail mesh as a "strip" of trail.
-        // each strip should *almost* act as an independent trail, but they all are drawn at the same time with the same parameters.
-        for strip in positions.split(|&v| v == Vec3::ZERO) {
-            let mut y_offset = 1.0;
-            for two_positions in strip.windows(2) {
-                let first = two_positions[0];
-                let second = two_positions[1];
-                // right side of the vector from first to second
-                let right_side = (second - first).normalize().cross(Vec3::Y).normalize();
-
-                let new_offset = (-1.0 * (first.distance(second) / height)) + y_offset;
-                let first_left = MarkerVertex {
-                    position: first - (right_side * horizontal_offset),
-                    texture_coordinates: vec2(0.0, y_offset),
-                    alpha,
-                    color,
-                    fade_near_far,
-                };
-                let first_right = MarkerVertex {
-                    position: first + (right_side * horizontal_offset),
-                    texture_coordinates: vec2(1.0, y_offset),
-                    alpha,
-                    color,
-                    fade_near_far,
-                };
-                let second_left = MarkerVertex {
-                    position: second - (right_side * horizontal_offset),
-                    texture_coordinates: vec2(0.0, new_offset),
-                    alpha,
-                    color,
-                    fade_near_far,
-                };
-                let second_right = MarkerVertex {
-                    position: second + (right_side * horizontal_offset),
-                    texture_coordinates: vec2(1.0, new_offset),
-                    alpha,
-                    color,
-                    fade_near_far,
-                };
-                y_offset = if new_offset.is_sign_positive() {
-                    new_offset
-                } else {
-                    1.0 - new_offset.fract().abs()
-                };
-                vertices.extend([
-                    second_left,
-                    first_left,
-                    first_right,
-                    first_right,
-                    second_right,
-                    second_left,
-                ]);
-            }
+        let vertices = build_trail_mesh(
+            positions,
+            &TrailStyle {
+                half_width: TrailStyle::DEFAULT_HALF_WIDTH
+                    * attrs.get_trail_scale().copied().unwrap_or(1.0),
+                is_wall: attrs.get_is_wall().unwrap_or_default(),
+                alpha,
+                color,
+                fade_near_far,
+            },
+        );
+        if vertices.is_empty() {
+            return None;
         }
 
         Some(ActiveTrail {
@@ -793,6 +1406,125 @@ impl ActiveTrail {
                 },
             },
             texture_handle: texture,
+            in_game_visibility: attrs.get_in_game_visibility().unwrap_or(true),
+            anim_speed: attrs.get_anim_speed().copied().unwrap_or_default(),
+            nodes: positions.into(),
+            map_attrs: MapAttributes::new(attrs, MapAttributes::DEFAULT_TRAIL_DISPLAY_SIZE),
+            map_color: {
+                let [r, g, b, a] = if color == [0; 4] { [255; 4] } else { color };
+                egui::Color32::from_rgba_unmultiplied(r, g, b, a).gamma_multiply(alpha)
+            },
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn marker(attrs: CommonAttributes) -> ActiveMarker {
+        let texture_handle = egui::Context::default().load_texture(
+            "test",
+            ColorImage::new([1, 1], egui::Color32::WHITE),
+            Default::default(),
+        );
+        ActiveMarker {
+            texture_id: 0,
+            texture_handle,
+            pos: Vec3::ZERO,
+            max_pixel_size: 2048.0,
+            min_pixel_size: 5.0,
+            attrs,
+            in_trigger_range: false,
+            guid: Uuid::nil(),
+            category: String::new(),
+            wakeup: None,
+            animation: Default::default(),
+            map_attrs: None,
+            options: Default::default(),
+        }
+    }
+
+    fn assert_vec3_eq(left: Vec3, right: Vec3) {
+        assert!(left.abs_diff_eq(right, 1e-5), "{left} != {right}");
+    }
+
+    #[test]
+    fn rotation_overrides() {
+        let mut attrs = CommonAttributes::default();
+        assert!(marker(attrs.clone()).rotation().is_none());
+        // rotate-x alone rotates only around x
+        attrs.set_rotate_x(Some(90.0));
+        let rotation = marker(attrs.clone()).rotation().unwrap();
+        assert_vec3_eq(rotation * Vec3::Y, Vec3::Z);
+        // rotate sets all three angles, rotate-x/y/z override them
+        attrs.set_rotate_x(None);
+        attrs.set_rotate(Some(vec3(10.0, 20.0, 30.0)));
+        attrs.set_rotate_y(Some(90.0));
+        let expected = Quat::from_euler(
+            EulerRot::XYZ,
+            10f32.to_radians(),
+            90f32.to_radians(),
+            30f32.to_radians(),
+        );
+        assert!(marker(attrs)
+            .rotation()
+            .unwrap()
+            .abs_diff_eq(expected, 1e-5));
+    }
+
+    #[test]
+    fn distance_fade_range() {
+        // fully visible until fade_near, then linear until fade_far
+        assert_eq!(distance_fade(5.0, 10.0, 20.0), 1.0);
+        assert_eq!(distance_fade(10.0, 10.0, 20.0), 1.0);
+        assert_eq!(distance_fade(15.0, 10.0, 20.0), 0.5);
+        assert_eq!(distance_fade(20.0, 10.0, 20.0), 0.0);
+        assert_eq!(distance_fade(25.0, 10.0, 20.0), 0.0);
+        // -1 disables that limit
+        assert_eq!(distance_fade(1000.0, -1.0, -1.0), 1.0);
+        assert_eq!(distance_fade(1000.0, 10.0, -1.0), 1.0);
+        assert_eq!(distance_fade(19.0, -1.0, 20.0), 1.0);
+        assert_eq!(distance_fade(20.0, -1.0, 20.0), 0.0);
+        // fade_far <= fade_near just cuts off at fade_far
+        assert_eq!(distance_fade(15.0, 20.0, 20.0), 1.0);
+        assert_eq!(distance_fade(20.0, 20.0, 20.0), 0.0);
+        assert_eq!(distance_fade(15.0, 30.0, 20.0), 1.0);
+        assert_eq!(distance_fade(25.0, 30.0, 20.0), 0.0);
+    }
+
+    #[test]
+    fn fixed_quad_orientation() {
+        let center = vec3(1.0, 2.0, 3.0);
+        // lies on the ground with the top edge towards +Z. iconSize is the half extent
+        let (corners, normal) = fixed_quad_corners(center, 2.0, Quat::IDENTITY, false);
+        assert_vec3_eq(corners[0], center + vec3(-2.0, 0.0, 2.0));
+        assert_vec3_eq(corners[1], center + vec3(-2.0, 0.0, -2.0));
+        assert_vec3_eq(corners[2], center + vec3(2.0, 0.0, -2.0));
+        assert_vec3_eq(corners[3], center + vec3(2.0, 0.0, 2.0));
+        assert_vec3_eq(normal, Vec3::Y);
+        // stands upright facing -Z
+        let (corners, normal) = fixed_quad_corners(center, 2.0, Quat::IDENTITY, true);
+        assert_vec3_eq(corners[0], center + vec3(-2.0, 2.0, 0.0));
+        assert_vec3_eq(corners[2], center + vec3(2.0, -2.0, 0.0));
+        assert_vec3_eq(normal, Vec3::NEG_Z);
+        // the normal is rotated along with the quad
+        let (corners, normal) =
+            fixed_quad_corners(center, 2.0, Quat::from_rotation_y(90f32.to_radians()), true);
+        assert_vec3_eq(normal, Vec3::NEG_X);
+        assert_vec3_eq(corners[0], center + vec3(0.0, 2.0, 2.0));
+    }
+
+    #[test]
+    fn fixed_quad_front_face_is_clockwise() {
+        // the front face is clockwise when seen from the side of the normal, for both orientations.
+        // in a right handed system, that means the cross product of the first triangle points away from the normal
+        for is_wall in [false, true] {
+            let rotation = Quat::from_euler(EulerRot::XYZ, 0.3, 1.2, -0.7);
+            let ([top_left, bottom_left, bottom_right, _], normal) =
+                fixed_quad_corners(Vec3::ZERO, 1.0, rotation, is_wall);
+            let winding = (bottom_left - top_left).cross(bottom_right - top_left);
+            assert!(winding.dot(normal) < 0.0, "is_wall: {is_wall}");
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use cap_std::fs_utf8::Dir;
+use egui::{Color32, LayerId, Pos2, Rect, Stroke, TextureId};
+use glam::{vec2, Vec2, Vec3};
+use jokoapi::end_point::{
+    maps::{Map, MapCache, MapGeometry},
+    EndPoint,
+};
+use jokolink::MumbleLink;
+use tracing::{error, info};
+
+use crate::pack::CommonAttributes;
+
+/// The map_rect/continent_rect of maps from the api, cached on disk inside the marker manager directory.
+/// Maps which are not in the cache are fetched in the background when we enter them.
+pub(crate) struct MapGeometries {
+    dir: Arc<Dir>,
+    cache: Arc<Mutex<MapCache>>,
+    /// held while saving the cache, so that the saves of concurrent fetches don't overwrite a newer file with an older snapshot.
+    /// the cache itself is only locked to serialize it, so that the ui thread never waits on the disk
+    save_lock: Arc<Mutex<()>>,
+    /// maps which are being fetched or failed to fetch. we only try once per session, so that we don't spam the api
+    requested: HashSet<u32>,
+}
+
+impl MapGeometries {
+    pub fn new(dir: Arc<Dir>) -> Self {
+        let cache = MapCache::load(&dir).unwrap_or_else(|e| {
+            error!(?e, "failed to load maps cache");
+            Default::default()
+        });
+        Self {
+            dir,
+            cache: Arc::new(Mutex::new(cache)),
+            save_lock: Default::default(),
+            requested: Default::default(),
+        }
+    }
+    /// geometry of the current map. If it is not cached yet, we start fetching it and use the player as the anchor until then.
+    pub fn get(&mut self, link: &MumbleLink) -> MapGeometry {
+        if let Some(map) = self.cache.lock().unwrap().get(link.map_id) {
+            return map.geometry();
+        }
+        if link.map_id != 0 && self.requested.insert(link.map_id) {
+            self.fetch(link.map_id);
+        }
+        MapGeometry::from_anchor(link.player_pos, vec2(link.player_x, link.player_y))
+    }
+    fn fetch(&self, map_id: u32) {
+        let dir = self.dir.clone();
+        let cache = self.cache.clone();
+        let save_lock = self.save_lock.clone();
+        rayon::spawn(move || {
+            let map = match Map::get_id(&ureq::Agent::new(), "", &map_id) {
+                Ok(map) => map,
+                Err(e) => {
+                    error!(?e, map_id, "failed to fetch map from api");
+                    return;
+                }
+            };
+            info!(map_id, map.name, "fetched map from api");
+            cache.lock().unwrap().insert(map);
+            let _save_guard = save_lock.lock().unwrap();
+            let json = match cache.lock().unwrap().take_json() {
+                Ok(Some(json)) => json,
+                // already saved by another fetch
+                Ok(None) => return,
+                Err(e) => {
+                    error!(?e, "failed to serialize maps cache");
+                    return;
+                }
+            };
+            if let Err(e) = MapCache::write_json(&dir, &json) {
+                error!(?e, "failed to save maps cache");
+            }
+        });
+    }
+}
+
+/// Projects continent coordinates onto the screen (in egui points) for the minimap or the world map
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MapProjection {
+    /// center of the map (or compass) on the screen
+    pub screen_center: Vec2,
+    /// the continent coordinates shown at the center of the map
+    pub map_center: Vec2,
+    /// size of one continent unit in egui points
+    pub points_per_unit: f32,
+    /// rotation of the compass in radians. zero for the world map or when compass rotation is disabled
+    pub rotation: f32,
+}
+
+impl MapProjection {
+    pub fn project(&self, continent: Vec2) -> Vec2 {
+        let offset = (continent - self.map_center) * self.points_per_unit;
+        let offset = if self.rotation != 0.0 {
+            Vec2::from_angle(-self.rotation).rotate(offset)
+        } else {
+            offset
+        };
+        self.screen_center + offset
+    }
+}
+
+/// The map related attributes of a marker or trail
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MapAttributes {
+    pub map_visibility: bool,
+    pub mini_map_visibility: bool,
+    /// size at normal ui scale, at zoom level 1 in pixels. for trails, this is the width of the trail
+    pub display_size: f32,
+    pub keep_on_map_edge: bool,
+    pub scale_on_map_with_zoom: bool,
+    /// markers disappear when the map is zoomed out beyond this scale
+    pub fade_out_scale_level: f32,
+}
+
+impl MapAttributes {
+    /// default taco map display size of markers in pixels
+    pub const DEFAULT_MARKER_DISPLAY_SIZE: f32 = 20.0;
+    /// default width of trails on the map in pixels
+    pub const DEFAULT_TRAIL_DISPLAY_SIZE: f32 = 4.0;
+    /// default taco map fade out scale level
+    pub const DEFAULT_FADE_OUT_SCALE_LEVEL: f32 = 100.0;
+
+    /// returns None if the object is neither visible on the map nor on the minimap
+    pub fn new(attrs: &CommonAttributes, default_display_size: f32) -> Option<Self> {
+        let map_visibility = attrs.get_map_visibility().unwrap_or(true);
+        let mini_map_visibility = attrs.get_mini_map_visibility().unwrap_or(true);
+        if !map_visibility && !mini_map_visibility {
+            return None;
+        }
+        Some(Self {
+            map_visibility,
+            mini_map_visibility,
+            display_size: attrs
+                .get_map_display_size()
+                .copied()
+                .unwrap_or(default_display_size),
+            keep_on_map_edge: attrs.get_keep_on_map_edge().unwrap_or_default(),
+            scale_on_map_with_zoom: attrs.get_scale_on_map_with_zoom().unwrap_or(true),
+            fade_out_scale_level: attrs
+                .get_map_fade_out_scale_level()
+                .copied()
+                .unwrap_or(Self::DEFAULT_FADE_OUT_SCALE_LEVEL),
+        })
+    }
+    fn is_visible(&self, world_map: bool, map_scale: f32) -> bool {
+        (if world_map {
+            self.map_visibility
+        } else {
+            self.mini_map_visibility
+        }) && map_scale <= self.fade_out_scale_level
+    }
+    /// size in egui points
+    fn size(&self, map_scale: f32, pixels_per_point: f32) -> f32 {
+        let size = self.display_size / pixels_per_point;
+        if self.scale_on_map_with_zoom && map_scale > 0.0 {
+            size / map_scale
+        } else {
+            size
+        }
+    }
+}
+
+/// A marker which will be drawn on the minimap/world map
+pub(crate) struct MapMarker {
+    /// map coordinates in meters
+    pub position: Vec3,
+    pub texture: TextureId,
+    pub tint: Color32,
+    pub attrs: MapAttributes,
+}
+
+/// A trail which will be drawn on the minimap/world map
+pub(crate) struct MapTrail {
+    /// map coordinates in meters. separate strips of the trail are split by [Vec3::ZERO]
+    pub nodes: Arc<[Vec3]>,
+    pub color: Color32,
+    pub attrs: MapAttributes,
+}
+
+/// This collects the markers and trails of all packs every frame and draws them on the minimap or the world map (when it is open)
+#[derive(Default)]
+pub(crate) struct MapLayer {
+    pub markers: Vec<MapMarker>,
+    pub trails: Vec<MapTrail>,
+}
+
+impl MapLayer {
+    /// gap between the bottom edge of gw2 window and the compass (when it is at the bottom right), in pixels
+    const COMPASS_BOTTOM_OFFSET: f32 = 36.0;
+    /// the world map scale in mumble is a little off compared to the minimap scale. found by trial and error (other overlays use the same value)
+    const WORLD_MAP_SCALE_FACTOR: f32 = 0.897;
+
+    pub fn clear(&mut self) {
+        self.markers.clear();
+        self.trails.clear();
+    }
+
+    pub fn gui(&self, etx: &egui::Context, link: &MumbleLink, geometry: MapGeometry) {
+        if self.markers.is_empty() && self.trails.is_empty() {
+            return;
+        }
+        let pixels_per_point = etx.pixels_per_point();
+        let screen_rect = etx.screen_rect();
+        let world_map = link.is_map_open();
+        if link.map_scale <= 0.0 {
+            return;
+        }
+        let (clip_rect, scale, rotation) = if world_map {
+            (
+                screen_rect,
+                link.map_scale * Self::WORLD_MAP_SCALE_FACTOR,
+                0.0,
+            )
+        } else {
+            if link.compass_width == 0 || link.compass_height == 0 {
+                return;
+            }
+            let size = egui::vec2(link.compass_width as f32, link.compass_height as f32)
+                / pixels_per_point;
+            let top = if link.is_compass_top_right() {
+                screen_rect.top()
+            } else {
+                screen_rect.bottom() - size.y - Self::COMPASS_BOTTOM_OFFSET / pixels_per_point
+            };
+            let rotation = if link.is_compass_rotation_enabled() {
+                link.compass_rotation
+            } else {
+                0.0
+            };
+            (
+                Rect::from_min_size(egui::pos2(screen_rect.right() - size.x, top), size),
+                link.map_scale,
+                rotation,
+            )
+        };
+        let projection = MapProjection {
+            screen_center: vec2(clip_rect.center().x, clip_rect.center().y),
+            map_center: vec2(link.map_center_x, link.map_center_y),
+            points_per_unit: 1.0 / (scale * pixels_per_point),
+            rotation,
+        };
+        let to_screen = |position: Vec3| -> Pos2 {
+            let pos = projection.project(geometry.meters_to_continent(position));
+            egui::pos2(pos.x, pos.y)
+        };
+        let painter = etx
+            .layer_painter(LayerId::background())
+            .with_clip_rect(clip_rect);
+
+        for trail in self.trails.iter() {
+            if !trail.attrs.is_visible(world_map, link.map_scale) {
+                continue;
+            }
+            let stroke = Stroke::new(
+                trail.attrs.size(link.map_scale, pixels_per_point),
+                trail.color,
+            );
+            for strip in trail.nodes.split(|&node| node == Vec3::ZERO) {
+                for segment in strip.windows(2) {
+                    painter.line_segment([to_screen(segment[0]), to_screen(segment[1])], stroke);
+                }
+            }
+        }
+        for marker in self.markers.iter() {
+            if !marker.attrs.is_visible(world_map, link.map_scale) {
+                continue;
+            }
+            let size = marker.attrs.size(link.map_scale, pixels_per_point);
+            let mut center = to_screen(marker.position);
+            if !clip_rect.contains(center) {
+                if !marker.attrs.keep_on_map_edge {
+                    continue;
+                }
+                center = clip_rect.shrink(size / 2.0).clamp(center);
+            }
+            painter.image(
+                marker.texture,
+                Rect::from_center_size(center, egui::vec2(size, size)),
+                Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0)),
+                marker.tint,
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn projection_rotates_around_screen_center() {
+        let projection = MapProjection {
+            screen_center: vec2(100.0, 100.0),
+            map_center: vec2(500.0, 500.0),
+            points_per_unit: 2.0,
+            rotation: 0.0,
+        };
+        assert_eq!(projection.project(vec2(510.0, 500.0)), vec2(120.0, 100.0));
+        let rotated = MapProjection {
+            rotation: std::f32::consts::FRAC_PI_2,
+            ..projection
+        };
+        assert!((rotated.project(vec2(510.0, 500.0)) - vec2(100.0, 80.0)).length() < 0.001);
+    }
+}
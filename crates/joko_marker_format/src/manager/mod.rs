@@ -15,7 +15,14 @@ editing a category's name/path means that you have to load all the maps that ref
 We will make not having a valid category/texture/tbin path as allowed. So, users can deal with the headache themselves.
 
 */
+mod animation;
+mod edge;
+mod info;
 mod live_pack;
+mod map_layer;
+pub mod spatial;
+pub mod timer;
+mod trail_mesh;
 use std::{
     collections::BTreeMap,
     io::Read,
@@ -31,7 +38,13 @@ use tracing::{error, info, info_span};
 use jokolink::MumbleLink;
 use miette::{Context, IntoDiagnostic, Result};
 
-use self::live_pack::LoadedPack;
+use self::{
+    edge::EdgeLayer,
+    info::{DistanceUnit, InfoLayer},
+    live_pack::{LoadedPack, DEFAULT_MAX_MARKER_DISTANCE},
+    map_layer::{MapGeometries, MapLayer},
+    timer::{Clock, SystemClock},
+};
 
 use super::pack::PackCore;
 
@@ -53,7 +66,7 @@ pub const MARKER_PACKS_DIRECTORY_NAME: &str = "packs";
 pub struct MarkerManager {
     /// holds data that is useful for the ui
     ui_data: MarkerManagerUI,
-    /// marker manager directory. not useful yet, but in future we could be using this to store config files etc..
+    /// marker manager directory. holds the maps cache. in future we could be using this to store config files etc..
     _marker_manager_dir: Arc<Dir>,
     /// packs directory which contains marker packs. each directory inside pack directory is an individual marker pack.
     /// The name of the child directory is the name of the pack
@@ -63,6 +76,22 @@ pub struct MarkerManager {
     /// The value is a loaded pack that contains additional data for live marker packs like what needs to be saved or category selections etc..
     packs: BTreeMap<String, LoadedPack>,
     missing_texture: Option<TextureHandle>,
+    /// info/title texts of the active markers which will be drawn this frame
+    info_layer: InfoLayer,
+    /// off-screen markers which will be pinned to the edge of the screen this frame
+    edge_layer: EdgeLayer,
+    /// markers and trails which will be drawn on the minimap/world map this frame
+    map_layer: MapLayer,
+    /// map_rect/continent_rect of maps to project the map layer onto the minimap/world map
+    map_geometries: MapGeometries,
+    /// markers farther than this distance (in meters) from the camera are not drawn
+    pub max_marker_distance: f32,
+    /// whether the closest markers window is open
+    pub show_closest_markers: bool,
+    /// number of markers in the closest markers window
+    pub closest_markers_count: usize,
+    /// source of current time for marker timers
+    clock: Box<dyn Clock>,
     /// This is the interval in number of seconds when we check if any of the packs need to be saved due to changes.
     /// This allows us to avoid saving the pack too often.
     pub save_interval: f64,
@@ -149,13 +178,22 @@ impl MarkerManager {
             }
         }
 
+        let marker_manager_dir: Arc<Dir> = marker_manager_dir.into();
         Ok(Self {
             packs,
             marker_packs_dir: marker_packs_dir.into(),
-            _marker_manager_dir: marker_manager_dir.into(),
+            map_geometries: MapGeometries::new(marker_manager_dir.clone()),
+            _marker_manager_dir: marker_manager_dir,
             ui_data: Default::default(),
             save_interval: 0.0,
             missing_texture: None,
+            info_layer: Default::default(),
+            edge_layer: Default::default(),
+            map_layer: Default::default(),
+            max_marker_distance: DEFAULT_MAX_MARKER_DISTANCE,
+            show_closest_markers: false,
+            closest_markers_count: 10,
+            clock: Box::new(SystemClock),
         })
     }
 
@@ -184,10 +222,13 @@ impl MarkerManager {
             }
         });
     }
+    /// replaces the source of current time for marker timers. eg: a fixed clock to get deterministic timers
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
     pub fn tick(
         &mut self,
         etx: &egui::Context,
-        timestamp: f64,
         joko_renderer: &mut joko_render::JokoRenderer,
         link: &Option<Arc<MumbleLink>>,
     ) {
@@ -204,15 +245,46 @@ impl MarkerManager {
             ));
         }
 
+        self.info_layer.clear();
+        self.edge_layer.clear();
+        let now = self.clock.now_utc();
         for pack in self.packs.values_mut() {
             pack.tick(
                 etx,
-                timestamp,
+                now,
                 joko_renderer,
                 link,
                 self.missing_texture.as_ref().unwrap(),
+                &mut self.info_layer,
+                &mut self.edge_layer,
+                self.max_marker_distance,
             );
         }
+        if self.show_closest_markers {
+            if let Some(link) = link.as_ref() {
+                for pack in self.packs.values() {
+                    pack.nearby_markers(
+                        link,
+                        now,
+                        self.max_marker_distance,
+                        &mut self.info_layer.nearby,
+                    );
+                }
+            }
+            self.info_layer.keep_closest(self.closest_markers_count);
+            self.info_layer
+                .closest_markers_gui(etx, &mut self.show_closest_markers);
+        }
+        self.info_layer.gui(etx, joko_renderer);
+        self.edge_layer.gui(etx, joko_renderer);
+
+        self.map_layer.clear();
+        if let Some(link) = link.as_ref() {
+            for pack in self.packs.values() {
+                pack.map_tick(now, &mut self.map_layer);
+            }
+            self.map_layer.gui(etx, link, self.map_geometries.get(link));
+        }
     }
     pub fn menu_ui(&mut self, ui: &mut egui::Ui) {
         ui.menu_button("Markers", |ui| {
@@ -223,6 +295,34 @@ impl MarkerManager {
     }
     pub fn gui(&mut self, etx: &egui::Context, open: &mut bool) {
         Window::new("Marker Manager").open(open).show(etx, |ui| -> Result<()> {
+            ui.horizontal(|ui| {
+                ui.label("max marker distance");
+                ui.add(
+                    egui::DragValue::new(&mut self.max_marker_distance)
+                        .clamp_range(10.0..=10000.0)
+                        .suffix(" m"),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("distance unit");
+                ui.radio_value(
+                    &mut self.info_layer.distance_unit,
+                    DistanceUnit::Meters,
+                    "meters",
+                );
+                ui.radio_value(
+                    &mut self.info_layer.distance_unit,
+                    DistanceUnit::GameUnits,
+                    "game units",
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_closest_markers, "closest markers");
+                ui.add(egui::DragValue::new(&mut self.closest_markers_count).clamp_range(1..=50));
+            });
+            CollapsingHeader::new("Timers").show(ui, |ui| {
+                self.info_layer.timers_ui(ui);
+            });
             CollapsingHeader::new("Loaded Packs").show(ui, |ui| {
                 egui::Grid::new("packs").striped(true).show(ui, |ui| {
                     let mut delete = vec![];
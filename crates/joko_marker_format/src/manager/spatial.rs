@@ -0,0 +1,336 @@
+//! Spatial index to avoid scanning every active marker of a map every frame.
+//! Dense packs can have thousands of markers in a single map, while only a few of them are visible at any time.
+
+use std::collections::HashMap;
+
+use glam::{Mat4, Vec3, Vec4};
+
+/// The six planes of the view frustum. The normals point inwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// extracts the frustum planes from a (left handed, 0..1 depth) view projection matrix like `JokoRenderer::view_proj`
+    pub fn from_view_proj(view_proj: Mat4) -> Self {
+        let [r0, r1, r2, r3] = [0, 1, 2, 3].map(|index| view_proj.row(index));
+        let planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r2, r3 - r2].map(|plane| {
+            let length = plane.truncate().length();
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        });
+        Self { planes }
+    }
+    /// whether a sphere is (atleast partially) inside the frustum
+    pub fn contains_sphere(&self, center: Vec3, radius: f32) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+    /// whether an axis aligned box is (atleast partially) inside the frustum. might return true for some boxes just outside the corners of the frustum
+    pub fn intersects_aabb(&self, min: Vec3, max: Vec3) -> bool {
+        self.planes.iter().all(|plane| {
+            let normal = plane.truncate();
+            // the corner of the box which is the farthest along the normal
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), max, min);
+            normal.dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cell {
+    min_y: f32,
+    max_y: f32,
+    /// the biggest radius among the entries of this cell
+    max_radius: f32,
+    /// index, position and radius
+    entries: Vec<(usize, Vec3, f32)>,
+}
+
+/// A uniform grid on the horizontal (XZ) plane. Each entry is an index (eg: of the marker in the map) with its position
+/// and the radius of the object around that position (eg: the height offset or size of the billboard).
+/// The grid is built once when the map changes, so it doesn't support updates.
+#[derive(Debug, Clone)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Cell>,
+    /// the biggest radius among all the entries
+    max_radius: f32,
+}
+
+impl Default for SpatialGrid {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_CELL_SIZE, [])
+    }
+}
+
+impl SpatialGrid {
+    /// in meters
+    pub const DEFAULT_CELL_SIZE: f32 = 64.0;
+
+    /// a grid of points (entries with zero radius)
+    pub fn new(cell_size: f32, entries: impl IntoIterator<Item = (usize, Vec3)>) -> Self {
+        Self::with_radii(
+            cell_size,
+            entries
+                .into_iter()
+                .map(|(index, position)| (index, position, 0.0)),
+        )
+    }
+    pub fn with_radii(
+        cell_size: f32,
+        entries: impl IntoIterator<Item = (usize, Vec3, f32)>,
+    ) -> Self {
+        let mut grid = Self {
+            cell_size,
+            cells: Default::default(),
+            max_radius: 0.0,
+        };
+        for (index, position, radius) in entries {
+            let cell = grid
+                .cells
+                .entry(grid.cell_of(position))
+                .or_insert_with(|| Cell {
+                    min_y: position.y,
+                    max_y: position.y,
+                    max_radius: 0.0,
+                    entries: vec![],
+                });
+            cell.min_y = cell.min_y.min(position.y);
+            cell.max_y = cell.max_y.max(position.y);
+            cell.max_radius = cell.max_radius.max(radius);
+            cell.entries.push((index, position, radius));
+            grid.max_radius = grid.max_radius.max(radius);
+        }
+        grid
+    }
+    pub fn len(&self) -> usize {
+        self.cells.values().map(|cell| cell.entries.len()).sum()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+    fn cell_of(&self, position: Vec3) -> (i32, i32) {
+        (
+            (position.x / self.cell_size).floor() as i32,
+            (position.z / self.cell_size).floor() as i32,
+        )
+    }
+    /// calls `f` for every cell whose bounds are within `max_distance` of `center`.
+    /// if `with_radius` is true, the bounds of each cell are grown by the biggest radius of its entries
+    fn for_each_cell_near(
+        &self,
+        center: Vec3,
+        max_distance: f32,
+        with_radius: bool,
+        mut f: impl FnMut(Vec3, Vec3, &Cell),
+    ) {
+        let reach = max_distance + if with_radius { self.max_radius } else { 0.0 };
+        let (min_x, min_z) = self.cell_of(center - Vec3::splat(reach));
+        let (max_x, max_z) = self.cell_of(center + Vec3::splat(reach));
+        let mut visit = |&(x, z): &(i32, i32), cell: &Cell| {
+            let margin = if with_radius { cell.max_radius } else { 0.0 };
+            let min = Vec3::new(
+                x as f32 * self.cell_size,
+                cell.min_y,
+                z as f32 * self.cell_size,
+            ) - Vec3::splat(margin);
+            let max = Vec3::new(
+                (x + 1) as f32 * self.cell_size,
+                cell.max_y,
+                (z + 1) as f32 * self.cell_size,
+            ) + Vec3::splat(margin);
+            if center.clamp(min, max).distance(center) <= max_distance {
+                f(min, max, cell);
+            }
+        };
+        let range_len = (max_x as i64 - min_x as i64 + 1) * (max_z as i64 - min_z as i64 + 1);
+        if range_len > self.cells.len() as i64 {
+            // when the distance is really big, it is cheaper to just check all the cells
+            for (key, cell) in self.cells.iter() {
+                visit(key, cell);
+            }
+        } else {
+            for x in min_x..=max_x {
+                for z in min_z..=max_z {
+                    if let Some(cell) = self.cells.get(&(x, z)) {
+                        visit(&(x, z), cell);
+                    }
+                }
+            }
+        }
+    }
+    /// pushes the indices of entries within `max_distance` of `center` AND inside the `frustum` into `out`.
+    /// the radius of each entry is taken into account, so a big object whose position is just outside is still included
+    pub fn query_frustum(
+        &self,
+        frustum: &Frustum,
+        center: Vec3,
+        max_distance: f32,
+        out: &mut Vec<usize>,
+    ) {
+        self.for_each_cell_near(center, max_distance, true, |min, max, cell| {
+            if !frustum.intersects_aabb(min, max) {
+                return;
+            }
+            for &(index, position, radius) in cell.entries.iter() {
+                if position.distance(center) <= max_distance + radius
+                    && frustum.contains_sphere(position, radius)
+                {
+                    out.push(index);
+                }
+            }
+        });
+    }
+    /// pushes the indices of entries within `radius` of `center` into `out`. the radii of the entries are ignored
+    pub fn query_sphere(&self, center: Vec3, radius: f32, out: &mut Vec<usize>) {
+        self.for_each_cell_near(center, radius, false, |_, _, cell| {
+            for &(index, position, _) in cell.entries.iter() {
+                if position.distance(center) <= radius {
+                    out.push(index);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// camera at the origin looking towards +Z with a 90 degree fov, so the side planes are at 45 degrees
+    fn frustum() -> Frustum {
+        let view = Mat4::look_at_lh(Vec3::ZERO, Vec3::Z, Vec3::Y);
+        let proj = Mat4::perspective_lh(std::f32::consts::FRAC_PI_2, 1.0, 1.0, 100.0);
+        Frustum::from_view_proj(proj * view)
+    }
+
+    fn query(grid: &SpatialGrid, center: Vec3, radius: f32) -> Vec<usize> {
+        let mut out = vec![];
+        grid.query_sphere(center, radius, &mut out);
+        out.sort_unstable();
+        out
+    }
+
+    #[test]
+    fn frustum_planes() {
+        let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+        let expected = [
+            // left, right, bottom, top
+            Vec4::new(diagonal, 0.0, diagonal, 0.0),
+            Vec4::new(-diagonal, 0.0, diagonal, 0.0),
+            Vec4::new(0.0, diagonal, diagonal, 0.0),
+            Vec4::new(0.0, -diagonal, diagonal, 0.0),
+            // near and far
+            Vec4::new(0.0, 0.0, 1.0, -1.0),
+            Vec4::new(0.0, 0.0, -1.0, 100.0),
+        ];
+        for (plane, expected) in frustum().planes.into_iter().zip(expected) {
+            assert!(plane.abs_diff_eq(expected, 1e-3), "{plane} != {expected}");
+        }
+    }
+
+    #[test]
+    fn frustum_aabb() {
+        let frustum = frustum();
+        let aabb = |min: [f32; 3], max: [f32; 3]| frustum.intersects_aabb(min.into(), max.into());
+        // in front of the camera
+        assert!(aabb([-1.0, -1.0, 10.0], [1.0, 1.0, 12.0]));
+        // behind the camera, beyond the far plane and left of the left plane
+        assert!(!aabb([-1.0, -1.0, -12.0], [1.0, 1.0, -10.0]));
+        assert!(!aabb([-1.0, -1.0, 101.0], [1.0, 1.0, 110.0]));
+        assert!(!aabb([-30.0, -1.0, 10.0], [-20.0, 1.0, 12.0]));
+        // straddling the left plane and the near plane
+        assert!(aabb([-30.0, -1.0, 10.0], [-5.0, 1.0, 12.0]));
+        assert!(aabb([-1.0, -1.0, -5.0], [1.0, 1.0, 5.0]));
+        // a sphere outside the left plane, but its radius reaches inside
+        assert!(!frustum.contains_sphere(Vec3::new(-12.0, 0.0, 10.0), 1.0));
+        assert!(frustum.contains_sphere(Vec3::new(-12.0, 0.0, 10.0), 2.0));
+    }
+
+    #[test]
+    fn grid_queries_across_cells() {
+        let grid = SpatialGrid::new(
+            10.0,
+            [
+                // on both sides of the border between cells (0, 0) and (1, 0)
+                (0, Vec3::new(9.5, 0.0, 5.0)),
+                (1, Vec3::new(10.5, 0.0, 5.0)),
+                // negative cells
+                (2, Vec3::new(-0.5, 0.0, -0.5)),
+                // high above the border
+                (3, Vec3::new(10.0, 50.0, 5.0)),
+            ],
+        );
+        assert_eq!(grid.len(), 4);
+        assert_eq!(query(&grid, Vec3::new(10.0, 0.0, 5.0), 1.0), vec![0, 1]);
+        assert_eq!(query(&grid, Vec3::new(0.0, 0.0, 0.0), 1.0), vec![2]);
+        assert_eq!(query(&grid, Vec3::new(10.0, 45.0, 5.0), 5.0), vec![3]);
+    }
+
+    #[test]
+    fn grid_query_max_distance() {
+        let grid = SpatialGrid::new(
+            10.0,
+            [
+                (0, Vec3::new(20.0, 0.0, 0.0)),
+                (1, Vec3::new(-20.5, 0.0, 0.0)),
+                (2, Vec3::new(0.0, 0.0, -20.0)),
+                (3, Vec3::new(0.0, 0.0, 19.0)),
+            ],
+        );
+        // exactly at max distance is included
+        assert_eq!(query(&grid, Vec3::ZERO, 20.0), vec![0, 2, 3]);
+        assert_eq!(query(&grid, Vec3::ZERO, 19.0), vec![3]);
+        // a huge distance checks all the cells instead of the cells in range
+        assert_eq!(query(&grid, Vec3::ZERO, 1e6), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn grid_query_frustum() {
+        let points = [
+            // in front
+            (0, Vec3::new(0.0, 0.0, 10.0)),
+            (1, Vec3::new(0.0, 0.0, 50.0)),
+            // behind the camera
+            (2, Vec3::new(0.0, 0.0, -10.0)),
+            // just left of the left plane
+            (3, Vec3::new(-11.0, 0.0, 10.0)),
+        ];
+        let query = |grid: &SpatialGrid, max_distance: f32| {
+            let mut out = vec![];
+            grid.query_frustum(&frustum(), Vec3::ZERO, max_distance, &mut out);
+            out.sort_unstable();
+            out
+        };
+        let grid = SpatialGrid::with_radii(10.0, points.map(|(index, pos)| (index, pos, 0.1)));
+        assert_eq!(query(&grid, 100.0), vec![0, 1]);
+        // the radius of the objects reaches into the frustum
+        let grid = SpatialGrid::with_radii(10.0, points.map(|(index, pos)| (index, pos, 1.0)));
+        assert_eq!(query(&grid, 100.0), vec![0, 1, 3]);
+        // max distance
+        assert_eq!(query(&grid, 20.0), vec![0, 3]);
+    }
+
+    #[test]
+    fn grid_radius_is_per_entry() {
+        // a huge object far away doesn't pull in the small ones next to it
+        let grid = SpatialGrid::with_radii(
+            10.0,
+            [
+                (0, Vec3::new(-60.0, 0.0, 10.0), 100.0),
+                (1, Vec3::new(-55.0, 0.0, 10.0), 1.0),
+                (2, Vec3::new(0.0, 0.0, 10.0), 1.0),
+            ],
+        );
+        let mut out = vec![];
+        grid.query_frustum(&frustum(), Vec3::ZERO, 100.0, &mut out);
+        out.sort_unstable();
+        assert_eq!(out, vec![0, 2]);
+    }
+}
@@ -0,0 +1,134 @@
+use time::{Duration, OffsetDateTime, Time};
+
+/// The source of the current time for marker timers.
+/// This lets us swap the system clock with a fixed/fake clock when we want deterministic timestamps (eg: tests).
+/// Use [crate::MarkerManager::set_clock] to replace the system clock.
+pub trait Clock {
+    /// current time in UTC
+    fn now_utc(&self) -> OffsetDateTime;
+}
+
+/// The default clock which just uses the system time
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_utc(&self) -> OffsetDateTime {
+        OffsetDateTime::now_utc()
+    }
+}
+
+/// start of the current day (midnight) in UTC. Daily reset of gw2 happens at this time.
+pub(crate) fn start_of_utc_day(now: OffsetDateTime) -> OffsetDateTime {
+    now.replace_time(Time::MIDNIGHT)
+}
+
+/// The next daily reset (00:00 UTC)
+pub(crate) fn next_daily_reset(now: OffsetDateTime) -> OffsetDateTime {
+    start_of_utc_day(now) + Duration::DAY
+}
+
+/// The next weekly reset (monday 07:30 UTC)
+pub(crate) fn next_weekly_reset(now: OffsetDateTime) -> OffsetDateTime {
+    let monday = start_of_utc_day(now)
+        - Duration::days(now.weekday().number_days_from_monday() as i64)
+        + Duration::hours(7)
+        + Duration::minutes(30);
+    if monday > now {
+        monday
+    } else {
+        monday + Duration::WEEK
+    }
+}
+
+/// The next time a map (or event) resets, for behavior 5 markers.
+/// The first reset of the day happens `reset_offset` seconds after the start of UTC day,
+/// and then it repeats every `reset_length` seconds.
+/// If `reset_length` is not positive, the map only resets once a day at `reset_offset`.
+pub(crate) fn next_map_reset(
+    now: OffsetDateTime,
+    reset_offset: f32,
+    reset_length: f32,
+) -> OffsetDateTime {
+    let day_start = start_of_utc_day(now);
+    let first_reset = day_start + Duration::seconds_f32(reset_offset);
+    if reset_length <= 0.0 {
+        return if first_reset > now {
+            first_reset
+        } else {
+            first_reset + Duration::DAY
+        };
+    }
+    let elapsed = (now - first_reset).as_seconds_f32();
+    let cycles = (elapsed / reset_length).floor() + 1.0;
+    first_reset + Duration::seconds_f32(cycles * reset_length)
+}
+
+/// formats the remaining time as `h:mm:ss` or `m:ss`
+pub(crate) fn format_countdown(remaining: Duration) -> String {
+    let total = remaining.whole_seconds().max(0);
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::{Date, Month};
+
+    /// 2024-01-01 is a monday
+    fn utc(day: u8, hour: u8, minute: u8, second: u8) -> OffsetDateTime {
+        Date::from_calendar_date(2024, Month::January, day)
+            .unwrap()
+            .with_hms(hour, minute, second)
+            .unwrap()
+            .assume_utc()
+    }
+
+    #[test]
+    fn weekly_reset_rolls_over_on_monday() {
+        assert_eq!(next_weekly_reset(utc(1, 7, 29, 59)), utc(1, 7, 30, 0));
+        assert_eq!(next_weekly_reset(utc(1, 7, 30, 0)), utc(8, 7, 30, 0));
+        assert_eq!(next_weekly_reset(utc(1, 0, 0, 0)), utc(1, 7, 30, 0));
+        assert_eq!(next_weekly_reset(utc(3, 12, 0, 0)), utc(8, 7, 30, 0));
+        assert_eq!(next_weekly_reset(utc(7, 23, 59, 59)), utc(8, 7, 30, 0));
+    }
+
+    #[test]
+    fn daily_reset_is_next_midnight() {
+        assert_eq!(next_daily_reset(utc(1, 13, 0, 0)), utc(2, 0, 0, 0));
+        assert_eq!(next_daily_reset(utc(1, 0, 0, 0)), utc(2, 0, 0, 0));
+        assert_eq!(next_daily_reset(utc(1, 23, 59, 59)), utc(2, 0, 0, 0));
+    }
+
+    #[test]
+    fn map_reset_uses_offset_and_length() {
+        // first reset at 00:30 and then every 2 hours
+        let next = |now| next_map_reset(now, 1800.0, 7200.0);
+        assert_eq!(next(utc(1, 0, 10, 0)), utc(1, 0, 30, 0));
+        assert_eq!(next(utc(1, 0, 30, 0)), utc(1, 2, 30, 0));
+        assert_eq!(next(utc(1, 3, 0, 0)), utc(1, 4, 30, 0));
+        assert_eq!(next(utc(1, 23, 0, 0)), utc(2, 0, 30, 0));
+        // no length. only resets once a day at the offset
+        assert_eq!(
+            next_map_reset(utc(1, 0, 10, 0), 1800.0, 0.0),
+            utc(1, 0, 30, 0)
+        );
+        assert_eq!(
+            next_map_reset(utc(1, 1, 0, 0), 1800.0, 0.0),
+            utc(2, 0, 30, 0)
+        );
+    }
+
+    #[test]
+    fn countdown_format() {
+        assert_eq!(format_countdown(Duration::seconds(3661)), "1:01:01");
+        assert_eq!(format_countdown(Duration::seconds(600)), "10:00");
+        assert_eq!(format_countdown(Duration::seconds(59)), "0:59");
+        assert_eq!(format_countdown(Duration::seconds(-5)), "0:00");
+    }
+}
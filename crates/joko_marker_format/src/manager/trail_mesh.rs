@@ -0,0 +1,414 @@
+//! Builds the triangles of a trail from its nodes.
+//! Each strip of the trail is a continuous ribbon. Corners are mitered, so that neighbouring segments share their vertices without gaps or overlaps.
+//! Sharp corners would need really long miters, so they get a round join on the outer side instead.
+//! The mesh is a list of quads (six vertices each), as the renderer sorts trails quad by quad. join triangles are paired into quads too.
+
+use glam::{vec2, Vec2, Vec3};
+use joko_render::billboard::MarkerVertex;
+
+/// How a trail looks. This is everything except the nodes that we need to build its mesh
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct TrailStyle {
+    /// half of the width of the trail in meters
+    pub half_width: f32,
+    /// walls stand upright from the nodes, instead of lying flat on the ground
+    pub is_wall: bool,
+    pub alpha: f32,
+    pub color: [u8; 4],
+    pub fade_near_far: Vec2,
+}
+
+impl TrailStyle {
+    /// default taco half width of trails in meters (20 inches)
+    pub const DEFAULT_HALF_WIDTH: f32 = 20.0 / crate::INCHES_PER_METER;
+    /// corners sharper than this need a miter longer than twice the half width. such corners are rounded instead
+    const MIN_MITER_COS: f32 = 0.5;
+    /// max angle (in radians) of each triangle of a round join
+    const ROUND_JOIN_STEP: f32 = std::f32::consts::PI / 8.0;
+}
+
+/// left and right edge of the ribbon at a node, with the distance along the trail (for texture coordinates)
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+    left: Vec3,
+    right: Vec3,
+    distance: f32,
+}
+
+/// builds the quads of a trail. the trail is split into separate strips by [Vec3::ZERO] nodes.
+/// the texture repeats every `2 * half_width` (the texture is square) along the trail, continuing across the corners.
+pub(crate) fn build_trail_mesh(nodes: &[Vec3], style: &TrailStyle) -> Vec<MarkerVertex> {
+    let mut vertices = vec![];
+    for strip in nodes.split(|&node| node == Vec3::ZERO) {
+        let mut strip = strip.to_vec();
+        // repeated nodes don't have a direction
+        strip.dedup();
+        if strip.len() < 2 {
+            continue;
+        }
+        if style.is_wall {
+            build_wall_strip(&strip, style, &mut vertices);
+        } else {
+            build_flat_strip(&strip, style, &mut vertices);
+        }
+    }
+    vertices
+}
+
+fn vertex(position: Vec3, texture_coordinates: Vec2, style: &TrailStyle) -> MarkerVertex {
+    MarkerVertex {
+        position,
+        alpha: style.alpha,
+        texture_coordinates,
+        fade_near_far: style.fade_near_far,
+        color: style.color,
+    }
+}
+
+/// `v` texture coordinate at the distance along the trail. starts at 1.0 and decreases (like taco)
+fn texture_v(distance: f32, style: &TrailStyle) -> f32 {
+    1.0 - distance / (style.half_width * 2.0)
+}
+
+fn push_quad(start: Edge, end: Edge, style: &TrailStyle, vertices: &mut Vec<MarkerVertex>) {
+    let start_v = texture_v(start.distance, style);
+    let end_v = texture_v(end.distance, style);
+    let start_left = vertex(start.left, vec2(0.0, start_v), style);
+    let start_right = vertex(start.right, vec2(1.0, start_v), style);
+    let end_left = vertex(end.left, vec2(0.0, end_v), style);
+    let end_right = vertex(end.right, vec2(1.0, end_v), style);
+    vertices.extend([
+        end_left,
+        start_left,
+        start_right,
+        start_right,
+        end_right,
+        end_left,
+    ]);
+}
+
+/// walls are just the nodes and the nodes moved up by the width of the trail. they never need joins
+fn build_wall_strip(strip: &[Vec3], style: &TrailStyle, vertices: &mut Vec<MarkerVertex>) {
+    let height = Vec3::Y * style.half_width * 2.0;
+    let mut distance = 0.0;
+    let mut previous: Option<(Vec3, Edge)> = None;
+    for &node in strip {
+        if let Some((previous_node, _)) = previous {
+            distance += previous_node.distance(node);
+        }
+        let edge = Edge {
+            left: node + height,
+            right: node,
+            distance,
+        };
+        if let Some((_, previous_edge)) = previous {
+            push_quad(previous_edge, edge, style, vertices);
+        }
+        previous = Some((node, edge));
+    }
+}
+
+/// the horizontal direction to the right of the segment from `first` to `second`
+fn right_side(first: Vec3, second: Vec3) -> Vec3 {
+    // left handed with y up
+    Vec3::Y
+        .cross(second - first)
+        .try_normalize()
+        // vertical segment. any horizontal direction will do
+        .unwrap_or(Vec3::X)
+}
+
+fn build_flat_strip(strip: &[Vec3], style: &TrailStyle, vertices: &mut Vec<MarkerVertex>) {
+    let width = style.half_width;
+    let sides: Vec<Vec3> = strip
+        .windows(2)
+        .map(|segment| right_side(segment[0], segment[1]))
+        .collect();
+    let mut distance = 0.0;
+    // the edge where the current segment starts
+    let mut start = Edge {
+        left: strip[0] - sides[0] * width,
+        right: strip[0] + sides[0] * width,
+        distance,
+    };
+    for (index, segment) in strip.windows(2).enumerate() {
+        let [first, node] = [segment[0], segment[1]];
+        distance += first.distance(node);
+        let side = sides[index];
+        let Some(&next_side) = sides.get(index + 1) else {
+            // last node of the strip
+            let end = Edge {
+                left: node - side * width,
+                right: node + side * width,
+                distance,
+            };
+            push_quad(start, end, style, vertices);
+            break;
+        };
+        let miter = (side + next_side).try_normalize();
+        let miter_cos = miter.map(|miter| miter.dot(side)).unwrap_or_default();
+        if let Some(miter) = miter.filter(|_| miter_cos >= TrailStyle::MIN_MITER_COS) {
+            // both segments share the mitered edge
+            let offset = miter * width / miter_cos;
+            let end = Edge {
+                left: node - offset,
+                right: node + offset,
+                distance,
+            };
+            push_quad(start, end, style, vertices);
+            start = end;
+            continue;
+        }
+        // sharp corner. the inner side still uses a (shortened) miter, and the outer side gets a round join.
+        // the trail turns towards the right side if the next segment goes in the direction of the current right side
+        let turns_right = (strip[index + 2] - node).dot(side) > 0.0;
+        let inner_sign = if turns_right { 1.0 } else { -1.0 };
+        let inner = node
+            + miter
+                .map(|miter| miter * width / miter_cos.max(TrailStyle::MIN_MITER_COS))
+                .unwrap_or(Vec3::ZERO)
+                * inner_sign;
+        let outer_end = node - side * width * inner_sign;
+        let outer_start = node - next_side * width * inner_sign;
+        let (end, next_start) = if turns_right {
+            (
+                Edge {
+                    left: outer_end,
+                    right: inner,
+                    distance,
+                },
+                Edge {
+                    left: outer_start,
+                    right: inner,
+                    distance,
+                },
+            )
+        } else {
+            (
+                Edge {
+                    left: inner,
+                    right: outer_end,
+                    distance,
+                },
+                Edge {
+                    left: inner,
+                    right: outer_start,
+                    distance,
+                },
+            )
+        };
+        push_quad(start, end, style, vertices);
+        push_round_join(
+            node,
+            outer_end,
+            outer_start,
+            texture_v(distance, style),
+            style,
+            vertices,
+        );
+        start = next_start;
+    }
+}
+
+/// fills the gap between `from` and `to` (both at half width from `center`) with a fan of triangles around `center`
+fn push_round_join(
+    center: Vec3,
+    from: Vec3,
+    to: Vec3,
+    v: f32,
+    style: &TrailStyle,
+    vertices: &mut Vec<MarkerVertex>,
+) {
+    let from_offset = from - center;
+    let to_offset = to - center;
+    let angle = from_offset.angle_between(to_offset);
+    let steps = (angle / TrailStyle::ROUND_JOIN_STEP).ceil().max(1.0) as usize;
+    // rotate around the vertical axis in the direction which takes `from` to `to`
+    let axis = if from_offset.cross(to_offset).y >= 0.0 {
+        Vec3::Y
+    } else {
+        Vec3::NEG_Y
+    };
+    let points: Vec<Vec3> = (0..=steps)
+        .map(|step| {
+            if step == 0 {
+                return from;
+            }
+            if step == steps {
+                return to;
+            }
+            let rotation = glam::Quat::from_axis_angle(axis, angle * step as f32 / steps as f32);
+            center + rotation * from_offset
+        })
+        .collect();
+    let center = vertex(center, vec2(0.5, v), style);
+    let mut triangles: Vec<[MarkerVertex; 3]> = points
+        .windows(2)
+        .map(|pair| {
+            [
+                center,
+                vertex(pair[0], vec2(0.5, v), style),
+                vertex(pair[1], vec2(0.5, v), style),
+            ]
+        })
+        .collect();
+    // pair the triangles into quads. the last one gets an empty triangle if needed
+    if !triangles.len().is_multiple_of(2) {
+        triangles.push([center; 3]);
+    }
+    vertices.extend(triangles.into_iter().flatten());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::vec3;
+
+    fn style(is_wall: bool) -> TrailStyle {
+        TrailStyle {
+            half_width: 1.0,
+            is_wall,
+            alpha: 1.0,
+            color: [0; 4],
+            fade_near_far: vec2(-1.0, -1.0),
+        }
+    }
+
+    /// (position, uv) of each vertex, rounded to avoid float noise
+    fn snapshot(vertices: &[MarkerVertex]) -> Vec<([f32; 3], [f32; 2])> {
+        let round = |value: f32| (value * 1000.0).round() / 1000.0 + 0.0;
+        vertices
+            .iter()
+            .map(|vertex| {
+                (
+                    vertex.position.to_array().map(round),
+                    vertex.texture_coordinates.to_array().map(round),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn straight_trail() {
+        // towards north. right side is east (+x)
+        let vertices = build_trail_mesh(&[vec3(1.0, 0.0, 0.0), vec3(1.0, 0.0, 4.0)], &style(false));
+        similar_asserts::assert_eq!(
+            snapshot(&vertices),
+            vec![
+                ([0.0, 0.0, 4.0], [0.0, -1.0]),
+                ([0.0, 0.0, 0.0], [0.0, 1.0]),
+                ([2.0, 0.0, 0.0], [1.0, 1.0]),
+                ([2.0, 0.0, 0.0], [1.0, 1.0]),
+                ([2.0, 0.0, 4.0], [1.0, -1.0]),
+                ([0.0, 0.0, 4.0], [0.0, -1.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn right_angle_is_mitered() {
+        // north and then east
+        let nodes = [
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, 3.0),
+            vec3(2.0, 0.0, 3.0),
+        ];
+        let vertices = build_trail_mesh(&nodes, &style(false));
+        similar_asserts::assert_eq!(
+            snapshot(&vertices),
+            vec![
+                // first segment ends at the miter
+                ([-1.0, 0.0, 4.0], [0.0, 0.0]),
+                ([-1.0, 0.0, 1.0], [0.0, 1.0]),
+                ([1.0, 0.0, 1.0], [1.0, 1.0]),
+                ([1.0, 0.0, 1.0], [1.0, 1.0]),
+                ([1.0, 0.0, 2.0], [1.0, 0.0]),
+                ([-1.0, 0.0, 4.0], [0.0, 0.0]),
+                // second segment starts at the same miter and the texture continues
+                ([2.0, 0.0, 4.0], [0.0, -1.0]),
+                ([-1.0, 0.0, 4.0], [0.0, 0.0]),
+                ([1.0, 0.0, 2.0], [1.0, 0.0]),
+                ([1.0, 0.0, 2.0], [1.0, 0.0]),
+                ([2.0, 0.0, 2.0], [1.0, -1.0]),
+                ([2.0, 0.0, 4.0], [0.0, -1.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn sharp_corner_is_rounded() {
+        // north and then almost back south, turning right
+        let nodes = [
+            vec3(0.0, 0.0, 1.0),
+            vec3(0.0, 0.0, 5.0),
+            vec3(0.5, 0.0, 1.0),
+        ];
+        let vertices = build_trail_mesh(&nodes, &style(false));
+        // two segments + the round join
+        assert!(vertices.len().is_multiple_of(6));
+        assert!(vertices.len() > 12);
+        let node = nodes[1];
+        for vertex in vertices[6..vertices.len() - 6].iter() {
+            // all join vertices are the node or on the circle around it, on the outer (north) side of the corner
+            let distance = vertex.position.distance(node);
+            assert!(distance < 0.001 || (distance - 1.0).abs() < 0.001);
+            assert!(vertex.position.z >= node.z - 0.001);
+        }
+        // the join starts where the first segment ends (outer left edge) and ends where the second segment starts
+        assert_eq!(vertices[0].position, vec3(-1.0, 0.0, 5.0));
+        assert_eq!(vertices[7].position, vertices[0].position);
+        let second_start_left = vertices[vertices.len() - 5].position;
+        assert!(vertices[vertices.len() - 6..]
+            .iter()
+            .any(|vertex| vertex.position == second_start_left));
+        assert!(vertices[6..vertices.len() - 6]
+            .iter()
+            .any(|vertex| vertex.position == second_start_left));
+    }
+
+    #[test]
+    fn wall_stands_upright() {
+        let nodes = [
+            vec3(0.0, 2.0, 0.0),
+            vec3(3.0, 2.0, 0.0),
+            vec3(3.0, 2.0, 3.0),
+        ];
+        let vertices = build_trail_mesh(&nodes, &style(true));
+        similar_asserts::assert_eq!(
+            snapshot(&vertices),
+            vec![
+                ([3.0, 4.0, 0.0], [0.0, -0.5]),
+                ([0.0, 4.0, 0.0], [0.0, 1.0]),
+                ([0.0, 2.0, 0.0], [1.0, 1.0]),
+                ([0.0, 2.0, 0.0], [1.0, 1.0]),
+                ([3.0, 2.0, 0.0], [1.0, -0.5]),
+                ([3.0, 4.0, 0.0], [0.0, -0.5]),
+                ([3.0, 4.0, 3.0], [0.0, -2.0]),
+                ([3.0, 4.0, 0.0], [0.0, -0.5]),
+                ([3.0, 2.0, 0.0], [1.0, -0.5]),
+                ([3.0, 2.0, 0.0], [1.0, -0.5]),
+                ([3.0, 2.0, 3.0], [1.0, -2.0]),
+                ([3.0, 4.0, 3.0], [0.0, -2.0]),
+            ]
+        );
+    }
+
+    #[test]
+    fn strips_are_separated_by_zero_nodes() {
+        let nodes = [
+            vec3(1.0, 0.0, 1.0),
+            vec3(1.0, 0.0, 2.0),
+            Vec3::ZERO,
+            vec3(5.0, 0.0, 1.0),
+            vec3(5.0, 0.0, 1.0),
+            vec3(5.0, 0.0, 2.0),
+            Vec3::ZERO,
+            vec3(9.0, 0.0, 9.0),
+        ];
+        let vertices = build_trail_mesh(&nodes, &style(false));
+        // one quad per strip. repeated nodes and single node strips are skipped
+        assert_eq!(vertices.len(), 12);
+        // texture restarts for each strip
+        assert_eq!(vertices[7].texture_coordinates, vec2(0.0, 1.0));
+    }
+}
@@ -0,0 +1,280 @@
+//! Texture atlas for marker icons.
+//! Markers are tiny icons, and most packs only use a few dozen of them. Instead of binding a texture for every marker,
+//! we copy the icons into one big texture and draw all the consecutive markers using the atlas with a single draw call.
+//! Icons which are too big (or don't fit anymore) are drawn with their own texture like before.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use egui::{ColorImage, ImageData, TextureId, TexturesDelta};
+use egui_render_three_d::three_d::{context::*, Context, HasContext};
+use glam::{vec2, Vec2};
+
+use crate::gl_error;
+
+/// A rectangle inside the atlas in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRegion {
+    /// maps the texture coordinates of the original texture (0.0..1.0) to the texture coordinates inside an atlas of `atlas_size`
+    pub fn remap(&self, uv: Vec2, atlas_size: u32) -> Vec2 {
+        let atlas_size = atlas_size as f32;
+        (vec2(self.x as f32, self.y as f32) + uv * vec2(self.width as f32, self.height as f32))
+            / atlas_size
+    }
+}
+
+/// The result of packing textures into an atlas
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AtlasLayout {
+    /// the location of each texture inside the atlas
+    pub regions: BTreeMap<u64, AtlasRegion>,
+    /// textures which are too big or didn't fit into the atlas
+    pub fallback: Vec<u64>,
+}
+
+/// Packs the textures (id and size) into a square atlas of `atlas_size` using shelves.
+/// The textures are sorted by height (and then width and id), so the same set of textures always produces the same layout,
+/// irrespective of the order in which they were given.
+/// `padding` is the empty space left around each texture to avoid bleeding of neighbours when sampling.
+pub fn pack_atlas(textures: &[(u64, [u32; 2])], atlas_size: u32, padding: u32) -> AtlasLayout {
+    let mut sorted = textures.to_vec();
+    sorted.sort_unstable_by(|(first_id, first), (second_id, second)| {
+        second[1]
+            .cmp(&first[1])
+            .then(second[0].cmp(&first[0]))
+            .then(first_id.cmp(second_id))
+    });
+    sorted.dedup_by_key(|(id, _)| *id);
+    let mut layout = AtlasLayout::default();
+    // top of the current shelf, its height and how much of its width is used
+    let (mut shelf_y, mut shelf_height, mut shelf_x) = (0u32, 0u32, 0u32);
+    for (id, [width, height]) in sorted {
+        let padded_width = width + padding * 2;
+        let padded_height = height + padding * 2;
+        if padded_width > atlas_size || padded_height > atlas_size {
+            layout.fallback.push(id);
+            continue;
+        }
+        if shelf_x + padded_width > atlas_size {
+            // start a new shelf
+            shelf_y += shelf_height;
+            shelf_x = 0;
+            shelf_height = 0;
+        }
+        if shelf_y + padded_height > atlas_size {
+            layout.fallback.push(id);
+            continue;
+        }
+        layout.regions.insert(
+            id,
+            AtlasRegion {
+                x: shelf_x + padding,
+                y: shelf_y + padding,
+                width,
+                height,
+            },
+        );
+        shelf_x += padded_width;
+        shelf_height = shelf_height.max(padded_height);
+    }
+    layout
+}
+
+/// Keeps a copy of the small managed egui textures and packs them into an atlas texture.
+/// The atlas is rebuilt (and uploaded) whenever a texture is added/updated/freed.
+#[derive(Default)]
+pub struct TextureAtlas {
+    images: BTreeMap<u64, Arc<ColorImage>>,
+    layout: AtlasLayout,
+    texture: Option<NativeTexture>,
+    dirty: bool,
+}
+
+impl TextureAtlas {
+    /// width and height of the atlas texture in pixels
+    pub const SIZE: u32 = 2048;
+    /// textures bigger than this (in width or height) are not added to the atlas
+    pub const MAX_ICON_SIZE: usize = 256;
+    pub const PADDING: u32 = 2;
+
+    /// copies the marker sized textures from the egui textures delta. must be called before egui painter consumes the delta
+    pub fn update(&mut self, textures_delta: &TexturesDelta) {
+        for (id, delta) in textures_delta.set.iter() {
+            let (TextureId::Managed(id), ImageData::Color(image)) = (id, &delta.image) else {
+                continue;
+            };
+            match delta.pos {
+                None => {
+                    if image.size[0] <= Self::MAX_ICON_SIZE && image.size[1] <= Self::MAX_ICON_SIZE
+                    {
+                        self.images.insert(*id, image.clone());
+                        self.dirty = true;
+                    } else if self.images.remove(id).is_some() {
+                        self.dirty = true;
+                    }
+                }
+                Some([x, y]) => {
+                    // partial update of a texture
+                    if let Some(existing) = self.images.get_mut(id) {
+                        let existing = Arc::make_mut(existing);
+                        for row in 0..image.size[1] {
+                            for column in 0..image.size[0] {
+                                if let Some(pixel) = existing
+                                    .pixels
+                                    .get_mut((y + row) * existing.size[0] + (x + column))
+                                {
+                                    *pixel = image.pixels[row * image.size[0] + column];
+                                }
+                            }
+                        }
+                        self.dirty = true;
+                    }
+                }
+            }
+        }
+        for id in textures_delta.free.iter() {
+            if let TextureId::Managed(id) = id {
+                if self.images.remove(id).is_some() {
+                    self.dirty = true;
+                }
+            }
+        }
+    }
+    /// the region of the texture inside the atlas. None if the texture is not in the atlas
+    pub fn region(&self, id: u64) -> Option<&AtlasRegion> {
+        // nothing is in the atlas until it is uploaded
+        self.texture.and_then(|_| self.layout.regions.get(&id))
+    }
+    pub fn texture(&self) -> Option<NativeTexture> {
+        self.texture
+    }
+    /// rebuilds and uploads the atlas texture if any of the textures changed
+    pub fn upload(&mut self, gl: &Context) {
+        if !std::mem::take(&mut self.dirty) {
+            return;
+        }
+        let sizes: Vec<(u64, [u32; 2])> = self
+            .images
+            .iter()
+            .map(|(id, image)| (*id, [image.size[0] as u32, image.size[1] as u32]))
+            .collect();
+        self.layout = pack_atlas(&sizes, Self::SIZE, Self::PADDING);
+        let size = Self::SIZE as usize;
+        let mut pixels = vec![0u8; size * size * 4];
+        for (id, region) in self.layout.regions.iter() {
+            let image = &self.images[id];
+            for row in 0..image.size[1] {
+                let src = &image.pixels[row * image.size[0]..(row + 1) * image.size[0]];
+                let dst_start = ((region.y as usize + row) * size + region.x as usize) * 4;
+                for (dst, src) in pixels[dst_start..dst_start + image.size[0] * 4]
+                    .chunks_exact_mut(4)
+                    .zip(src)
+                {
+                    dst.copy_from_slice(&src.to_array());
+                }
+            }
+        }
+        unsafe {
+            gl_error!(gl);
+            let texture = match self.texture {
+                Some(texture) => texture,
+                None => {
+                    let texture = gl.create_texture().expect("failed to create atlas texture");
+                    self.texture = Some(texture);
+                    texture
+                }
+            };
+            gl.bind_texture(TEXTURE_2D, Some(texture));
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MIN_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_MAG_FILTER, LINEAR as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_S, CLAMP_TO_EDGE as i32);
+            gl.tex_parameter_i32(TEXTURE_2D, TEXTURE_WRAP_T, CLAMP_TO_EDGE as i32);
+            // same format as egui textures
+            gl.tex_image_2d(
+                TEXTURE_2D,
+                0,
+                SRGB8_ALPHA8 as i32,
+                Self::SIZE as i32,
+                Self::SIZE as i32,
+                0,
+                RGBA,
+                UNSIGNED_BYTE,
+                Some(&pixels),
+            );
+            gl_error!(gl);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn packing_is_deterministic() {
+        let textures = [(1, [32, 32]), (2, [64, 16]), (3, [16, 64]), (4, [32, 32])];
+        let mut reversed = textures;
+        reversed.reverse();
+        let layout = pack_atlas(&textures, 128, 1);
+        assert_eq!(layout, pack_atlas(&reversed, 128, 1));
+        assert!(layout.fallback.is_empty());
+        // tallest texture goes first
+        assert_eq!(
+            layout.regions[&3],
+            AtlasRegion {
+                x: 1,
+                y: 1,
+                width: 16,
+                height: 64
+            }
+        );
+    }
+
+    #[test]
+    fn regions_do_not_overlap() {
+        let textures: Vec<_> = (0..40)
+            .map(|id| (id, [8 + (id as u32 * 7) % 40, 8 + (id as u32 * 13) % 40]))
+            .collect();
+        let layout = pack_atlas(&textures, 256, 2);
+        let regions: Vec<_> = layout.regions.values().collect();
+        for (index, first) in regions.iter().enumerate() {
+            assert!(first.x + first.width <= 256 && first.y + first.height <= 256);
+            for second in regions[index + 1..].iter() {
+                let separate = first.x + first.width <= second.x
+                    || second.x + second.width <= first.x
+                    || first.y + first.height <= second.y
+                    || second.y + second.height <= first.y;
+                assert!(separate, "{first:?} overlaps {second:?}");
+            }
+        }
+        assert_eq!(layout.regions.len() + layout.fallback.len(), textures.len());
+    }
+
+    #[test]
+    fn oversized_textures_fall_back() {
+        let layout = pack_atlas(&[(1, [300, 10]), (2, [10, 10]), (3, [100, 100])], 128, 0);
+        assert_eq!(layout.fallback, vec![1]);
+        assert!(layout.regions.contains_key(&2) && layout.regions.contains_key(&3));
+        // atlas is full
+        let layout = pack_atlas(&[(1, [100, 100]), (2, [100, 100])], 128, 0);
+        assert_eq!(layout.fallback, vec![2]);
+    }
+
+    #[test]
+    fn remap_uv() {
+        let region = AtlasRegion {
+            x: 64,
+            y: 32,
+            width: 32,
+            height: 32,
+        };
+        assert_eq!(region.remap(vec2(0.0, 0.0), 128), vec2(0.5, 0.25));
+        assert_eq!(region.remap(vec2(1.0, 1.0), 128), vec2(0.75, 0.5));
+    }
+}
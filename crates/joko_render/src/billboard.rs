@@ -8,7 +8,11 @@ use egui_render_three_d::{
 use glam::{Vec2, Vec3};
 use tracing::{error, info, warn};
 
-use crate::gl_error;
+use crate::{
+    atlas::TextureAtlas,
+    gl_error,
+    sorting::{apply_occlusion_hints, sort_markers_back_to_front, sort_trails_back_to_front},
+};
 
 const MARKER_VERTEX_STRIDE: i32 = std::mem::size_of::<MarkerVertex>() as _;
 pub struct BillBoardRenderer {
@@ -18,6 +22,25 @@ pub struct BillBoardRenderer {
     vao: NativeVertexArray,
     vb: NativeBuffer,
     trail_buffers: Vec<NativeBuffer>,
+    /// atlas of marker icons, so that we can draw most of the markers in a few draw calls
+    pub atlas: TextureAtlas,
+    /// the draw calls for markers of this frame
+    batches: Vec<MarkerBatch>,
+    /// dim the markers between the camera and the player
+    pub occlusion_hints: bool,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatchTexture {
+    Atlas,
+    /// texture which is not in the atlas
+    Managed(u64),
+}
+/// consecutive markers (after sorting) that use the same texture and can be drawn with a single draw call
+#[derive(Debug, Clone, Copy)]
+struct MarkerBatch {
+    texture: BatchTexture,
+    first_marker: usize,
+    count: usize,
 }
 pub struct TrailObject {
     pub vertices: Arc<[MarkerVertex]>,
@@ -68,6 +91,9 @@ impl BillBoardRenderer {
                 trails: Vec::new(),
                 trail_buffers: Default::default(),
                 vao,
+                atlas: Default::default(),
+                batches: Default::default(),
+                occlusion_hints: false,
             }
         }
     }
@@ -75,14 +101,21 @@ impl BillBoardRenderer {
         self.markers.clear();
         self.trails.clear();
     }
-    pub fn prepare_render_data(&mut self, _link: &jokolink::MumbleLink, gl: &Context) {
+    pub fn prepare_render_data(&mut self, link: &jokolink::MumbleLink, gl: &Context) {
         unsafe {
             gl_error!(gl);
         }
-        // sort by depth
-        self.markers.sort_unstable_by(|first, second| {
-            first.distance.total_cmp(&second.distance).reverse() // we need the farther markers (more distance from camera) to be rendered first, for correct alpha blending
-        });
+        // we need the farther markers (more distance from camera) to be rendered first, for correct alpha blending
+        sort_markers_back_to_front(&mut self.markers, link.cam_pos);
+        sort_trails_back_to_front(&mut self.trails, link.cam_pos);
+        if self.occlusion_hints {
+            apply_occlusion_hints(
+                &mut self.markers,
+                link.cam_pos,
+                link.f_camera_front,
+                link.player_pos,
+            );
+        }
 
         let mut required_size_in_bytes =
             (self.markers.len() * 6 * std::mem::size_of::<MarkerVertex>()) as u64;
@@ -93,8 +126,29 @@ impl BillBoardRenderer {
         let mut vb = vec![];
         vb.reserve(self.markers.len() * 6 * std::mem::size_of::<MarkerVertex>());
 
-        for marker_object in self.markers.iter() {
-            vb.extend_from_slice(&marker_object.vertices);
+        self.atlas.upload(gl);
+        self.batches.clear();
+        for (index, marker_object) in self.markers.iter().enumerate() {
+            let mut vertices = marker_object.vertices;
+            let texture = match self.atlas.region(marker_object.texture) {
+                Some(region) => {
+                    for vertex in vertices.iter_mut() {
+                        vertex.texture_coordinates =
+                            region.remap(vertex.texture_coordinates, TextureAtlas::SIZE);
+                    }
+                    BatchTexture::Atlas
+                }
+                None => BatchTexture::Managed(marker_object.texture),
+            };
+            vb.extend_from_slice(&vertices);
+            match self.batches.last_mut() {
+                Some(batch) if batch.texture == texture => batch.count += 1,
+                _ => self.batches.push(MarkerBatch {
+                    texture,
+                    first_marker: index,
+                    count: 1,
+                }),
+            }
         }
         unsafe {
             gl_error!(gl);
@@ -155,12 +209,34 @@ impl BillBoardRenderer {
             gl.bind_vertex_buffer(0, Some(self.vb), 0, MARKER_VERTEX_STRIDE);
 
             gl.bind_buffer(ARRAY_BUFFER, Some(self.vb));
-            for (index, mo) in self.markers.iter().enumerate() {
-                let index: u32 = index.try_into().unwrap();
-                if let Some(texture) = textures.get(&mo.texture) {
-                    gl.bind_texture(TEXTURE_2D, Some(texture.handle));
-                    gl.bind_sampler(0, Some(texture.sampler));
-                    gl.draw_arrays(TRIANGLES, index as i32 * 6, 6);
+            for batch in self.batches.iter() {
+                let bound = match batch.texture {
+                    BatchTexture::Atlas => {
+                        if let Some(texture) = self.atlas.texture() {
+                            gl.bind_texture(TEXTURE_2D, Some(texture));
+                            // atlas uses its own texture parameters
+                            gl.bind_sampler(0, None);
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                    BatchTexture::Managed(id) => {
+                        if let Some(texture) = textures.get(&id) {
+                            gl.bind_texture(TEXTURE_2D, Some(texture.handle));
+                            gl.bind_sampler(0, Some(texture.sampler));
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                };
+                if bound {
+                    gl.draw_arrays(
+                        TRIANGLES,
+                        (batch.first_marker * 6) as i32,
+                        (batch.count * 6) as i32,
+                    );
                 }
             }
             gl_error!(gl);
@@ -186,7 +262,7 @@ pub struct MarkerObject {
     pub texture: u64,
     /// The distance from camera
     /// As markers have transparency, we need to render them from far -> near order
-    /// So, we will sort them using this distance just before rendering
+    /// So, we will recalculate it and sort them using this distance just before rendering
     pub distance: f32,
 }
 
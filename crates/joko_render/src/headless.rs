@@ -0,0 +1,96 @@
+//! A surfaceless EGL context to render without any window. Mostly useful for screenshot tests in CI.
+//! With mesa, this works without a gpu or a display server by falling back to the llvmpipe software renderer.
+//! use `LIBGL_ALWAYS_SOFTWARE=1` env to force llvmpipe, so that the output doesn't depend on the gpu driver.
+
+use khronos_egl as egl;
+use miette::{miette, Result};
+
+/// <https://registry.khronos.org/EGL/extensions/MESA/EGL_MESA_platform_surfaceless.txt>
+const PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+/// An opengl 4.5 core context which is current on the thread that created it.
+/// The context doesn't have a default framebuffer, so render into the framebuffer of [crate::JokoRenderer::new_offscreen].
+pub struct HeadlessContext {
+    egl: egl::DynamicInstance<egl::EGL1_5>,
+    display: egl::Display,
+    context: egl::Context,
+}
+
+impl HeadlessContext {
+    pub fn new() -> Result<Self> {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+            .map_err(|e| miette!("failed to load libEGL: {e}"))?;
+        let display = unsafe {
+            egl.get_platform_display(
+                PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY,
+                &[egl::ATTRIB_NONE],
+            )
+        }
+        .map_err(|e| miette!("failed to get surfaceless display: {e}"))?;
+        let (major, minor) = egl
+            .initialize(display)
+            .map_err(|e| miette!("failed to initialize egl: {e}"))?;
+        tracing::info!(major, minor, "initialized egl");
+        egl.bind_api(egl::OPENGL_API)
+            .map_err(|e| miette!("failed to bind opengl api: {e}"))?;
+        let config = egl
+            .choose_first_config(
+                display,
+                // there are no window surfaces on a surfaceless display, and the default is WINDOW_BIT
+                &[
+                    egl::RENDERABLE_TYPE,
+                    egl::OPENGL_BIT,
+                    egl::SURFACE_TYPE,
+                    0,
+                    egl::NONE,
+                ],
+            )
+            .map_err(|e| miette!("failed to choose egl config: {e}"))?
+            .ok_or_else(|| miette!("no egl config supports opengl"))?;
+        let context = egl
+            .create_context(
+                display,
+                config,
+                None,
+                &[
+                    egl::CONTEXT_MAJOR_VERSION,
+                    4,
+                    egl::CONTEXT_MINOR_VERSION,
+                    5,
+                    egl::CONTEXT_OPENGL_PROFILE_MASK,
+                    egl::CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                    egl::NONE,
+                ],
+            )
+            .map_err(|e| miette!("failed to create opengl 4.5 context: {e}"))?;
+        egl.make_current(display, None, None, Some(context))
+            .map_err(|e| miette!("failed to make context current: {e}"))?;
+        Ok(Self {
+            egl,
+            display,
+            context,
+        })
+    }
+    /// to be used as the loader function for [crate::JokoRenderer::new_offscreen]
+    pub fn get_proc_address(&self, name: &str) -> *const std::ffi::c_void {
+        self.egl
+            .get_proc_address(name)
+            .map(|f| f as *const std::ffi::c_void)
+            .unwrap_or(std::ptr::null())
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        if let Err(e) = self.egl.make_current(self.display, None, None, None) {
+            tracing::error!(?e, "failed to release headless context");
+        }
+        if let Err(e) = self.egl.destroy_context(self.display, self.context) {
+            tracing::error!(?e, "failed to destroy headless context");
+        }
+        if let Err(e) = self.egl.terminate(self.display) {
+            tracing::error!(?e, "failed to terminate egl display");
+        }
+    }
+}
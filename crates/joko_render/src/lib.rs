@@ -1,4 +1,8 @@
+pub mod atlas;
 pub mod billboard;
+#[cfg(feature = "headless")]
+pub mod headless;
+pub mod sorting;
 use billboard::BillBoardRenderer;
 use billboard::MarkerObject;
 use billboard::TrailObject;
@@ -6,6 +10,11 @@ use egui_render_three_d::three_d;
 use egui_render_three_d::three_d::context::COLOR_BUFFER_BIT;
 use egui_render_three_d::three_d::context::DEPTH_BUFFER_BIT;
 use egui_render_three_d::three_d::context::STENCIL_BUFFER_BIT;
+use egui_render_three_d::three_d::context::{
+    NativeFramebuffer, NativeRenderbuffer, COLOR_ATTACHMENT0, DEPTH24_STENCIL8,
+    DEPTH_STENCIL_ATTACHMENT, FRAMEBUFFER, FRAMEBUFFER_COMPLETE, PACK_ALIGNMENT, RENDERBUFFER,
+    RGBA, SRGB8_ALPHA8, UNSIGNED_BYTE,
+};
 use egui_render_three_d::three_d::Camera;
 use egui_render_three_d::three_d::HasContext;
 use egui_render_three_d::three_d::ScissorBox;
@@ -37,6 +46,64 @@ pub struct JokoRenderer {
     pub link: Option<Arc<MumbleLink>>,
     pub billboard_renderer: BillBoardRenderer,
     pub gl: egui_render_three_d::ThreeDBackend,
+    /// the framebuffer we render into when there's no window. see [Self::new_offscreen]
+    offscreen: Option<OffscreenTarget>,
+}
+
+/// A framebuffer with color and depth/stencil renderbuffers, which replaces the default framebuffer of a window.
+struct OffscreenTarget {
+    framebuffer: NativeFramebuffer,
+    color: NativeRenderbuffer,
+    depth_stencil: NativeRenderbuffer,
+}
+
+impl OffscreenTarget {
+    unsafe fn new(gl: &three_d::Context, size: [u32; 2]) -> Self {
+        let framebuffer = gl
+            .create_framebuffer()
+            .expect("failed to create offscreen framebuffer");
+        let color = gl
+            .create_renderbuffer()
+            .expect("failed to create color renderbuffer");
+        let depth_stencil = gl
+            .create_renderbuffer()
+            .expect("failed to create depth renderbuffer");
+        let target = Self {
+            framebuffer,
+            color,
+            depth_stencil,
+        };
+        target.resize(gl, size);
+        gl.bind_framebuffer(FRAMEBUFFER, Some(framebuffer));
+        gl.framebuffer_renderbuffer(FRAMEBUFFER, COLOR_ATTACHMENT0, RENDERBUFFER, Some(color));
+        gl.framebuffer_renderbuffer(
+            FRAMEBUFFER,
+            DEPTH_STENCIL_ATTACHMENT,
+            RENDERBUFFER,
+            Some(depth_stencil),
+        );
+        let status = gl.check_framebuffer_status(FRAMEBUFFER);
+        assert_eq!(
+            status, FRAMEBUFFER_COMPLETE,
+            "offscreen framebuffer is incomplete"
+        );
+        gl_error!(gl);
+        target
+    }
+    unsafe fn resize(&self, gl: &three_d::Context, size: [u32; 2]) {
+        // same format as the srgb framebuffer of the window
+        gl.bind_renderbuffer(RENDERBUFFER, Some(self.color));
+        gl.renderbuffer_storage(RENDERBUFFER, SRGB8_ALPHA8, size[0] as i32, size[1] as i32);
+        gl.bind_renderbuffer(RENDERBUFFER, Some(self.depth_stencil));
+        gl.renderbuffer_storage(
+            RENDERBUFFER,
+            DEPTH24_STENCIL8,
+            size[0] as i32,
+            size[1] as i32,
+        );
+        gl.bind_renderbuffer(RENDERBUFFER, None);
+        gl_error!(gl);
+    }
 }
 
 impl JokoRenderer {
@@ -50,11 +117,40 @@ impl JokoRenderer {
             glfw_backend.window.raw_window_handle(),
             glfw_backend.framebuffer_size_physical,
         );
+        Self::with_backend(backend, glfw_backend.framebuffer_size_physical, None)
+    }
+    /// Creates a renderer which draws into an offscreen framebuffer of `framebuffer_size` instead of a window.
+    /// Use [Self::read_pixels] to get the rendered frame.
+    ///
+    /// # Safety
+    /// An opengl 4.5 context (eg: [headless::HeadlessContext] or a hidden window) must be current on this thread
+    /// and `get_proc_address` must load the functions of that context.
+    pub unsafe fn new_offscreen(
+        get_proc_address: impl FnMut(&str) -> *const std::ffi::c_void,
+        framebuffer_size: [u32; 2],
+    ) -> Self {
+        // the window handle is only used on web
+        let backend = ThreeDBackend::new(
+            ThreeDConfig {
+                glow_config: Default::default(),
+            },
+            get_proc_address,
+            raw_window_handle::RawWindowHandle::Xlib(raw_window_handle::XlibWindowHandle::empty()),
+            framebuffer_size,
+        );
+        let offscreen = OffscreenTarget::new(&backend.context, framebuffer_size);
+        Self::with_backend(backend, framebuffer_size, Some(offscreen))
+    }
+    fn with_backend(
+        backend: ThreeDBackend,
+        framebuffer_size: [u32; 2],
+        offscreen: Option<OffscreenTarget>,
+    ) -> Self {
         let viewport = Viewport {
             x: 0,
             y: 0,
-            width: glfw_backend.framebuffer_size_physical[0],
-            height: glfw_backend.framebuffer_size_physical[1],
+            width: framebuffer_size[0],
+            height: framebuffer_size[1],
         };
         let gl = &backend.context;
         unsafe { gl_error!(gl) };
@@ -76,6 +172,7 @@ impl JokoRenderer {
             gl: backend,
             billboard_renderer,
             cam_pos: Default::default(),
+            offscreen,
         }
     }
     pub fn get_z_near(&self) -> f32 {
@@ -109,6 +206,21 @@ impl JokoRenderer {
         }
         self.link = link;
     }
+    /// Projects a world position into screen coordinates using [Self::view_proj].
+    /// `screen_size` is the size of the screen in whatever units you want the result to be in (eg: egui points).
+    /// The origin of the returned coordinates is the top left corner of the screen.
+    /// returns None if the position is behind the camera.
+    pub fn world_to_screen(&self, pos: glam::Vec3, screen_size: glam::Vec2) -> Option<glam::Vec2> {
+        let clip = self.view_proj * pos.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(glam::vec2(
+            (ndc.x + 1.0) * 0.5 * screen_size.x,
+            (1.0 - ndc.y) * 0.5 * screen_size.y,
+        ))
+    }
     pub fn add_billboard(&mut self, marker_object: MarkerObject) {
         self.billboard_renderer.markers.push(marker_object);
     }
@@ -117,6 +229,15 @@ impl JokoRenderer {
     }
     pub fn prepare_frame(&mut self, latest_framebuffer_size_getter: impl FnMut() -> [u32; 2]) {
         self.billboard_renderer.prepare_frame();
+        if let Some(offscreen) = self.offscreen.as_ref() {
+            unsafe {
+                self.gl
+                    .context
+                    .bind_framebuffer(FRAMEBUFFER, Some(offscreen.framebuffer));
+                // there's no window to set the default viewport, so a surfaceless context starts with an empty viewport
+                self.gl.context.set_viewport(self.viewport);
+            }
+        }
         self.gl.prepare_frame(latest_framebuffer_size_getter);
         unsafe {
             let gl = self.gl.context.clone();
@@ -140,6 +261,7 @@ impl JokoRenderer {
         textures_delta: egui::TexturesDelta,
         logical_screen_size: [f32; 2],
     ) {
+        self.billboard_renderer.atlas.update(&textures_delta);
         if let Some(link) = self.link.as_ref() {
             self.billboard_renderer
                 .prepare_render_data(link, &self.gl.context);
@@ -156,6 +278,40 @@ impl JokoRenderer {
 
     pub fn present(&mut self) {}
 
+    /// Reads the rendered frame from the framebuffer (offscreen or the back buffer of the window).
+    /// returns the pixels as tightly packed RGBA8 rows, starting from the top row. same layout as `image::RgbaImage`
+    pub fn read_pixels(&self) -> Vec<u8> {
+        let [width, height] = [self.viewport.width as usize, self.viewport.height as usize];
+        let mut pixels = vec![0u8; width * height * 4];
+        unsafe {
+            let gl = &self.gl.context;
+            gl.bind_framebuffer(
+                FRAMEBUFFER,
+                self.offscreen
+                    .as_ref()
+                    .map(|offscreen| offscreen.framebuffer),
+            );
+            gl.pixel_store_i32(PACK_ALIGNMENT, 1);
+            gl.read_pixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                RGBA,
+                UNSIGNED_BYTE,
+                three_d::context::PixelPackData::Slice(&mut pixels),
+            );
+            gl_error!(gl);
+        }
+        // opengl starts from the bottom row
+        let row_len = width * 4;
+        let mut flipped = Vec::with_capacity(pixels.len());
+        for row in pixels.chunks_exact(row_len).rev() {
+            flipped.extend_from_slice(row);
+        }
+        flipped
+    }
+
     pub fn resize_framebuffer(&mut self, latest_size: [u32; 2]) {
         tracing::info!(?latest_size, "resizing framebuffer");
 
@@ -165,6 +321,23 @@ impl JokoRenderer {
             width: latest_size[0],
             height: latest_size[1],
         };
+        if let Some(offscreen) = self.offscreen.as_ref() {
+            unsafe { offscreen.resize(&self.gl.context, latest_size) };
+        }
         self.gl.resize_framebuffer(latest_size);
     }
 }
+
+impl Drop for JokoRenderer {
+    fn drop(&mut self) {
+        if let Some(offscreen) = self.offscreen.take() {
+            unsafe {
+                let gl = &self.gl.context;
+                gl.bind_framebuffer(FRAMEBUFFER, None);
+                gl.delete_framebuffer(offscreen.framebuffer);
+                gl.delete_renderbuffer(offscreen.color);
+                gl.delete_renderbuffer(offscreen.depth_stencil);
+            }
+        }
+    }
+}
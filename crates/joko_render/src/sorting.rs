@@ -0,0 +1,163 @@
+//! Preparing the marker/trail vertices for blending, before they are uploaded to the gpu.
+//! Markers and trails are translucent, so they must be drawn from back to front (farthest from camera first) to blend correctly.
+//! Nothing in here touches opengl.
+
+use std::cmp::Reverse;
+
+use glam::Vec3;
+
+use crate::billboard::{MarkerObject, MarkerVertex, TrailObject};
+
+/// alpha multiplier of the markers that are dimmed by occlusion hints
+pub const OCCLUSION_HINT_ALPHA: f32 = 0.3;
+
+/// average position of the vertices
+pub fn center(vertices: &[MarkerVertex]) -> Vec3 {
+    if vertices.is_empty() {
+        return Vec3::ZERO;
+    }
+    vertices.iter().map(|vertex| vertex.position).sum::<Vec3>() / vertices.len() as f32
+}
+
+/// sort key for back to front order. distances are never negative, so their bit patterns sort in the same order as the floats
+fn back_to_front_key(position: Vec3, cam_pos: Vec3) -> Reverse<u32> {
+    Reverse(position.distance(cam_pos).to_bits())
+}
+
+/// sets [MarkerObject::distance] to the distance between the camera and the center of the marker quad, and sorts the markers from back to front.
+/// markers at the same distance keep their order, so that there's no flickering between them
+pub fn sort_markers_back_to_front(markers: &mut [MarkerObject], cam_pos: Vec3) {
+    for marker in markers.iter_mut() {
+        marker.distance = center(&marker.vertices).distance(cam_pos);
+    }
+    markers.sort_by_key(|marker| Reverse(marker.distance.to_bits()));
+}
+
+/// sorts the quads (six vertices each) of a trail from back to front. returns the sorted vertices and the distance of the farthest quad
+pub fn sort_trail_segments_back_to_front(
+    vertices: &[MarkerVertex],
+    cam_pos: Vec3,
+) -> (Vec<MarkerVertex>, f32) {
+    let mut segments: Vec<&[MarkerVertex]> = vertices.chunks_exact(6).collect();
+    segments.sort_by_cached_key(|segment| back_to_front_key(center(segment), cam_pos));
+    let farthest = segments
+        .first()
+        .map(|segment| center(segment).distance(cam_pos))
+        .unwrap_or_default();
+    (segments.concat(), farthest)
+}
+
+/// sorts the segments of each trail from back to front, and then sorts the trails by their farthest segment.
+/// trails are still drawn one after another (as they use different textures), so segments of different trails are not interleaved.
+pub fn sort_trails_back_to_front(trails: &mut Vec<TrailObject>, cam_pos: Vec3) {
+    let mut sorted: Vec<(f32, TrailObject)> = trails
+        .drain(..)
+        .map(|trail| {
+            let (vertices, farthest) = sort_trail_segments_back_to_front(&trail.vertices, cam_pos);
+            (
+                farthest,
+                TrailObject {
+                    vertices: vertices.into(),
+                    texture: trail.texture,
+                },
+            )
+        })
+        .collect();
+    sorted.sort_by_key(|(farthest, _)| Reverse(farthest.to_bits()));
+    trails.extend(sorted.into_iter().map(|(_, trail)| trail));
+}
+
+/// Occlusion hints dim the markers which are between the camera and the player (i.e. behind the player, from the player's view),
+/// so that they don't hide the character or the things that the player is looking at.
+pub fn apply_occlusion_hints(
+    markers: &mut [MarkerObject],
+    cam_pos: Vec3,
+    camera_front: Vec3,
+    player_pos: Vec3,
+) {
+    let player_depth = (player_pos - cam_pos).dot(camera_front);
+    for marker in markers.iter_mut() {
+        let depth = (center(&marker.vertices) - cam_pos).dot(camera_front);
+        if depth < player_depth {
+            for vertex in marker.vertices.iter_mut() {
+                vertex.alpha *= OCCLUSION_HINT_ALPHA;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{vec2, Vec2};
+
+    fn quad(position: Vec3) -> [MarkerVertex; 6] {
+        [MarkerVertex {
+            position,
+            alpha: 1.0,
+            texture_coordinates: Vec2::ZERO,
+            fade_near_far: vec2(-1.0, -1.0),
+            color: [0; 4],
+        }; 6]
+    }
+    fn marker(position: Vec3, texture: u64) -> MarkerObject {
+        MarkerObject {
+            vertices: quad(position),
+            texture,
+            distance: 0.0,
+        }
+    }
+
+    #[test]
+    fn markers_are_sorted_back_to_front() {
+        let mut markers = vec![
+            marker(Vec3::Z * 5.0, 1),
+            marker(Vec3::Z * 50.0, 2),
+            marker(Vec3::Z * 20.0, 3),
+            marker(Vec3::X * 20.0, 4),
+        ];
+        sort_markers_back_to_front(&mut markers, Vec3::ZERO);
+        let order: Vec<u64> = markers.iter().map(|marker| marker.texture).collect();
+        // 3 and 4 are at the same distance and keep their order
+        assert_eq!(order, vec![2, 3, 4, 1]);
+        assert_eq!(markers[0].distance, 50.0);
+    }
+
+    #[test]
+    fn trails_are_sorted_back_to_front() {
+        let near_trail: Vec<MarkerVertex> = [1.0, 3.0, 2.0]
+            .into_iter()
+            .flat_map(|z| quad(Vec3::Z * z))
+            .collect();
+        let far_trail: Vec<MarkerVertex> = [10.0, 30.0]
+            .into_iter()
+            .flat_map(|z| quad(Vec3::Z * z))
+            .collect();
+        let mut trails = vec![
+            TrailObject {
+                vertices: near_trail.into(),
+                texture: 1,
+            },
+            TrailObject {
+                vertices: far_trail.into(),
+                texture: 2,
+            },
+        ];
+        sort_trails_back_to_front(&mut trails, Vec3::ZERO);
+        assert_eq!(trails[0].texture, 2);
+        let depths: Vec<f32> = trails[1]
+            .vertices
+            .chunks_exact(6)
+            .map(|segment| center(segment).z)
+            .collect();
+        assert_eq!(depths, vec![3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn occlusion_hints_dim_markers_in_front_of_player() {
+        let mut markers = vec![marker(Vec3::Z * 2.0, 1), marker(Vec3::Z * 20.0, 2)];
+        apply_occlusion_hints(&mut markers, Vec3::ZERO, Vec3::Z, Vec3::Z * 5.0);
+        assert_eq!(markers[0].vertices[0].alpha, OCCLUSION_HINT_ALPHA);
+        assert_eq!(markers[1].vertices[0].alpha, 1.0);
+    }
+}
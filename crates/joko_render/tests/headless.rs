@@ -0,0 +1,193 @@
+//! screenshot tests using the offscreen renderer. run with `cargo test -p joko_render --features headless`
+//! needs libEGL with the surfaceless platform (mesa). set `LIBGL_ALWAYS_SOFTWARE=1` to use llvmpipe (that's what CI does).
+//! The frames are compared against the golden images in `tests/golden`. If you change the rendering on purpose,
+//! run with `JOKO_UPDATE_GOLDEN=1` to overwrite them and check the new images before committing.
+#![cfg(feature = "headless")]
+
+use std::sync::Arc;
+
+use egui::{epaint::ImageDelta, Color32, ColorImage, TextureId, TextureOptions, TexturesDelta};
+use glam::{vec2, Vec2, Vec3};
+use joko_render::{
+    billboard::{MarkerObject, MarkerVertex, TrailObject},
+    headless::HeadlessContext,
+    JokoRenderer,
+};
+use jokolink::MumbleLink;
+
+const SIZE: [u32; 2] = [64, 64];
+/// how much a channel of a pixel may differ from the golden image. drivers don't filter/blend exactly the same way
+const CHANNEL_TOLERANCE: u8 = 8;
+/// how many pixels may differ more than [CHANNEL_TOLERANCE]. drivers also don't agree on the pixels at the edges of triangles
+const MAX_MISMATCHED_PIXELS: usize = 16;
+const UPDATE_GOLDEN_ENV: &str = "JOKO_UPDATE_GOLDEN";
+
+/// compares `pixels` against `tests/golden/{name}.png`. on failure, the frame is saved in the target dir for inspection
+fn assert_golden(name: &str, pixels: Vec<u8>) {
+    let golden_path =
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/golden/{name}.png"));
+    let frame = image::RgbaImage::from_raw(SIZE[0], SIZE[1], pixels).unwrap();
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        frame.save(&golden_path).unwrap();
+        return;
+    }
+    let golden = image::open(&golden_path)
+        .unwrap_or_else(|e| {
+            panic!(
+                "failed to open {golden_path:?}: {e}. run with {UPDATE_GOLDEN_ENV}=1 to create it"
+            )
+        })
+        .into_rgba8();
+    assert_eq!(golden.dimensions(), frame.dimensions());
+    let mismatched = golden
+        .pixels()
+        .zip(frame.pixels())
+        .filter(|(golden, actual)| {
+            golden
+                .0
+                .iter()
+                .zip(actual.0)
+                .any(|(golden, actual)| golden.abs_diff(actual) > CHANNEL_TOLERANCE)
+        })
+        .count();
+    if mismatched > MAX_MISMATCHED_PIXELS {
+        let actual_path =
+            std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.png"));
+        frame.save(&actual_path).unwrap();
+        panic!("{mismatched} pixels differ from {golden_path:?}. the frame is saved at {actual_path:?}");
+    }
+}
+
+fn red_and_green_textures() -> TexturesDelta {
+    let texture = |id, color| {
+        (
+            TextureId::Managed(id),
+            ImageDelta::full(ColorImage::new([4, 4], color), TextureOptions::LINEAR),
+        )
+    };
+    TexturesDelta {
+        set: vec![texture(1, Color32::RED), texture(2, Color32::GREEN)],
+        free: vec![],
+    }
+}
+
+/// renders the objects with the camera at the origin looking towards +Z.
+/// trails use the textures of the egui painter, which are only uploaded at the end of a frame.
+/// so, the first frame uploads the textures and the second one draws the objects
+fn render(markers: Vec<MarkerObject>, trails: Vec<TrailObject>) -> Vec<u8> {
+    let context = HeadlessContext::new().expect("failed to create headless context");
+    let mut renderer =
+        unsafe { JokoRenderer::new_offscreen(|name| context.get_proc_address(name), SIZE) };
+    renderer.tick(Some(Arc::new(MumbleLink {
+        cam_pos: Vec3::ZERO,
+        f_camera_front: Vec3::Z,
+        player_pos: Vec3::Z * 5.0,
+        fov: 1.0,
+        ..Default::default()
+    })));
+    let logical_size = [SIZE[0] as f32, SIZE[1] as f32];
+    renderer.prepare_frame(|| SIZE);
+    renderer.render_egui(vec![], red_and_green_textures(), logical_size);
+    renderer.prepare_frame(|| SIZE);
+    for marker in markers {
+        renderer.add_billboard(marker);
+    }
+    for trail in trails {
+        renderer.add_trail(trail);
+    }
+    renderer.render_egui(vec![], Default::default(), logical_size);
+    renderer.read_pixels()
+}
+
+fn pixel(pixels: &[u8], x: u32, y: u32) -> [u8; 4] {
+    let index = ((y * SIZE[0] + x) * 4) as usize;
+    pixels[index..index + 4].try_into().unwrap()
+}
+
+/// a marker quad facing the camera (which looks towards +Z) at `center`
+fn marker(center: Vec3, half_size: f32, texture: u64) -> MarkerObject {
+    let vertex = |offset: Vec2, uv: Vec2| vertex(center + offset.extend(0.0) * half_size, uv);
+    let top_left = vertex(vec2(-1.0, 1.0), vec2(0.0, 0.0));
+    let top_right = vertex(vec2(1.0, 1.0), vec2(1.0, 0.0));
+    let bottom_left = vertex(vec2(-1.0, -1.0), vec2(0.0, 1.0));
+    let bottom_right = vertex(vec2(1.0, -1.0), vec2(1.0, 1.0));
+    MarkerObject {
+        vertices: [
+            top_left,
+            bottom_left,
+            bottom_right,
+            bottom_right,
+            top_right,
+            top_left,
+        ],
+        texture,
+        distance: 0.0,
+    }
+}
+
+fn vertex(position: Vec3, texture_coordinates: Vec2) -> MarkerVertex {
+    MarkerVertex {
+        position,
+        alpha: 1.0,
+        texture_coordinates,
+        fade_near_far: vec2(-1.0, -1.0),
+        color: [0; 4],
+    }
+}
+
+/// a flat trail on the ground (y = -1) going straight away from the camera, through the given z coordinates
+fn trail(nodes: &[f32], half_width: f32, texture: u64) -> TrailObject {
+    let vertices: Vec<MarkerVertex> = nodes
+        .windows(2)
+        .flat_map(|segment| {
+            let [near, far] = [segment[0], segment[1]];
+            let near_left = vertex(Vec3::new(-half_width, -1.0, near), vec2(0.0, near));
+            let near_right = vertex(Vec3::new(half_width, -1.0, near), vec2(1.0, near));
+            let far_left = vertex(Vec3::new(-half_width, -1.0, far), vec2(0.0, far));
+            let far_right = vertex(Vec3::new(half_width, -1.0, far), vec2(1.0, far));
+            [
+                near_left, far_left, far_right, far_right, near_right, near_left,
+            ]
+        })
+        .collect();
+    TrailObject {
+        vertices: vertices.into(),
+        texture,
+    }
+}
+
+#[test]
+fn renders_marker_offscreen() {
+    let pixels = render(vec![marker(Vec3::Z * 10.0, 2.0, 1)], vec![]);
+    assert_eq!(pixels.len(), (SIZE[0] * SIZE[1] * 4) as usize);
+    // marker covers the center of the screen
+    assert_eq!(pixel(&pixels, 32, 32), [255, 0, 0, 255]);
+    // and nothing else is drawn
+    assert_eq!(pixel(&pixels, 1, 1), [0, 0, 0, 0]);
+    assert_eq!(pixel(&pixels, 62, 62), [0, 0, 0, 0]);
+    assert_golden("single_marker", pixels);
+}
+
+#[test]
+fn blends_markers_back_to_front() {
+    // the translucent green marker is added first, but it is closer. so, it must be drawn over the red one
+    let mut green = marker(Vec3::new(1.0, 1.0, 8.0), 1.5, 2);
+    for vertex in green.vertices.iter_mut() {
+        vertex.alpha = 0.5;
+    }
+    let pixels = render(
+        vec![green, marker(Vec3::new(-1.0, -1.0, 12.0), 2.0, 1)],
+        vec![],
+    );
+    assert_golden("blended_markers", pixels);
+}
+
+#[test]
+fn renders_trail() {
+    let pixels = render(vec![], vec![trail(&[2.0, 6.0, 30.0], 0.5, 2)]);
+    // the trail starts below the center of the screen and narrows towards the horizon
+    assert_eq!(pixel(&pixels, 32, 50), [0, 255, 0, 255]);
+    assert_eq!(pixel(&pixels, 32, 20), [0, 0, 0, 0]);
+    assert_eq!(pixel(&pixels, 5, 50), [0, 0, 0, 0]);
+    assert_golden("trail", pixels);
+}
@@ -13,8 +13,12 @@ pub use serde::{Deserialize, Serialize};
 // pub mod outfits;
 // pub mod quaggans;
 // pub mod races;
+pub mod continents;
+pub mod maps;
 pub mod mounts;
+pub mod professions;
 pub mod races;
+pub mod specializations;
 pub mod worlds;
 const AUTHORIZATION_HEADER_NAME: &str = "Authorization";
 
@@ -0,0 +1,34 @@
+use crate::prelude::*;
+
+/// <https://wiki.guildwars2.com/wiki/API:2/continents>
+/// There's only two continents. Tyria (1) and The Mists (2)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Continent {
+    pub id: u32,
+    pub name: String,
+    /// width and height of the continent in continent coordinates
+    pub continent_dims: [u32; 2],
+    pub min_zoom: u32,
+    pub max_zoom: u32,
+    pub floors: Vec<i32>,
+}
+impl EndPoint for Continent {
+    type Id = u32;
+    const URL: &'static str = const_format::concatcp!(API_BASE_V2_URL, "/continents");
+    const AUTH: bool = false;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_continent() {
+        let continent: Continent =
+            serde_json::from_str(include_str!("fixtures/continent_1.json")).unwrap();
+        assert_eq!(continent.id, 1);
+        assert_eq!(continent.name, "Tyria");
+        assert_eq!(continent.continent_dims, [81920, 114688]);
+        assert_eq!(continent.max_zoom, 8);
+    }
+}
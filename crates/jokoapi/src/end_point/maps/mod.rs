@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+
+use cap_std::fs_utf8::Dir;
+use glam::{vec2, DVec2, Vec2, Vec3};
+
+use crate::prelude::*;
+
+/// The game world coordinates are in inches and mumble link gives us positions in meters.
+pub const INCHES_PER_METER: f32 = 39.37;
+/// One continent coordinate unit is 24 inches on every map that we care about.
+pub const INCHES_PER_CONTINENT_UNIT: f64 = 24.0;
+
+/// <https://wiki.guildwars2.com/wiki/API:2/maps>
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Map {
+    pub id: u32,
+    pub name: String,
+    pub min_level: u32,
+    pub max_level: u32,
+    pub default_floor: i32,
+    /// Center, Instance, Public, Tutorial, Pvp etc..
+    #[serde(rename = "type")]
+    pub map_type: String,
+    pub floors: Vec<i32>,
+    #[serde(default)]
+    pub region_id: u32,
+    #[serde(default)]
+    pub region_name: String,
+    #[serde(default)]
+    pub continent_id: u32,
+    #[serde(default)]
+    pub continent_name: String,
+    /// bottom left and top right corners of the map in game world coordinates (inches). y grows towards north
+    pub map_rect: [[f64; 2]; 2],
+    /// top left and bottom right corners of the map in continent coordinates. y grows towards south
+    pub continent_rect: [[f64; 2]; 2],
+}
+impl EndPoint for Map {
+    type Id = u32;
+    const URL: &'static str = const_format::concatcp!(API_BASE_V2_URL, "/maps");
+    const AUTH: bool = false;
+}
+
+impl Map {
+    pub fn geometry(&self) -> MapGeometry {
+        MapGeometry {
+            map_rect: self.map_rect.map(DVec2::from),
+            continent_rect: self.continent_rect.map(DVec2::from),
+        }
+    }
+}
+
+/// Converts between the different coordinate systems of a map.
+/// 1. world coordinates in inches (map_rect, trail/marker positions in the game). x grows towards east and y towards north.
+/// 2. mumble link coordinates in meters ([Vec3] like player_pos). x grows towards east, y is height and z grows towards north.
+/// 3. continent coordinates (continent_rect, mumble's player_x/y). x grows towards east and y towards south.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapGeometry {
+    pub map_rect: [DVec2; 2],
+    pub continent_rect: [DVec2; 2],
+}
+
+impl MapGeometry {
+    /// A fallback for when we don't have the map's rects yet (eg: still fetching from the api).
+    /// mumble gives us the player's position in both meters and continent coordinates, so we use the player as the anchor
+    /// and assume [INCHES_PER_CONTINENT_UNIT]. This is only accurate as long as the map isn't scaled, which is true for most maps.
+    pub fn from_anchor(meters: Vec3, continent: Vec2) -> Self {
+        let inches = Self::meters_to_inches(meters);
+        let continent = continent.as_dvec2();
+        Self {
+            map_rect: [inches, inches + DVec2::splat(INCHES_PER_CONTINENT_UNIT)],
+            continent_rect: [
+                DVec2::new(continent.x, continent.y - 1.0),
+                DVec2::new(continent.x + 1.0, continent.y),
+            ],
+        }
+    }
+    /// world coordinates (inches) to continent coordinates
+    pub fn inches_to_continent(&self, inches: DVec2) -> DVec2 {
+        let [map_min, map_max] = self.map_rect;
+        let [continent_min, continent_max] = self.continent_rect;
+        let ratio = (inches - map_min) / (map_max - map_min);
+        // continent y is flipped
+        let ratio = DVec2::new(ratio.x, 1.0 - ratio.y);
+        continent_min + ratio * (continent_max - continent_min)
+    }
+    /// continent coordinates to world coordinates (inches)
+    pub fn continent_to_inches(&self, continent: DVec2) -> DVec2 {
+        let [map_min, map_max] = self.map_rect;
+        let [continent_min, continent_max] = self.continent_rect;
+        let ratio = (continent - continent_min) / (continent_max - continent_min);
+        let ratio = DVec2::new(ratio.x, 1.0 - ratio.y);
+        map_min + ratio * (map_max - map_min)
+    }
+    /// mumble link position (meters) to world coordinates (inches). height is dropped
+    pub fn meters_to_inches(meters: Vec3) -> DVec2 {
+        DVec2::new(meters.x as f64, meters.z as f64) * INCHES_PER_METER as f64
+    }
+    /// world coordinates (inches) to mumble link position (meters) at the given height (in meters)
+    pub fn inches_to_meters(inches: DVec2, height: f32) -> Vec3 {
+        let meters = inches / INCHES_PER_METER as f64;
+        Vec3::new(meters.x as f32, height, meters.y as f32)
+    }
+    /// mumble link position (meters) to continent coordinates
+    pub fn meters_to_continent(&self, meters: Vec3) -> Vec2 {
+        let continent = self.inches_to_continent(Self::meters_to_inches(meters));
+        vec2(continent.x as f32, continent.y as f32)
+    }
+    /// continent coordinates to mumble link position (meters) at the given height (in meters)
+    pub fn continent_to_meters(&self, continent: Vec2, height: f32) -> Vec3 {
+        Self::inches_to_meters(self.continent_to_inches(continent.as_dvec2()), height)
+    }
+}
+
+/// An on-disk cache of map metadata, so that we don't need to hit the api every time we change maps.
+/// map data rarely changes (only with game updates), so we never invalidate the cache.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MapCache {
+    maps: BTreeMap<u32, Map>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl MapCache {
+    pub const FILE_NAME: &'static str = "maps_cache.json";
+
+    /// loads the cache from [Self::FILE_NAME] inside `dir`. If the file doesn't exist, returns an empty cache
+    pub fn load(dir: &Dir) -> Result<Self> {
+        if !dir.exists(Self::FILE_NAME) {
+            return Ok(Default::default());
+        }
+        let json = dir
+            .read_to_string(Self::FILE_NAME)
+            .into_diagnostic()
+            .wrap_err("failed to read maps cache")?;
+        serde_json::from_str(&json)
+            .into_diagnostic()
+            .wrap_err("failed to deserialize maps cache")
+    }
+    /// writes the cache to disk if any new maps were added since it was loaded/saved
+    pub fn save(&mut self, dir: &Dir) -> Result<()> {
+        match self.take_json()? {
+            Some(json) => Self::write_json(dir, &json),
+            None => Ok(()),
+        }
+    }
+    /// serializes the cache if any new maps were added since it was loaded/saved.
+    /// Together with [Self::write_json], this lets the caller do the IO without holding a lock on the cache.
+    pub fn take_json(&mut self) -> Result<Option<String>> {
+        if !std::mem::take(&mut self.dirty) {
+            return Ok(None);
+        }
+        serde_json::to_string_pretty(&self)
+            .into_diagnostic()
+            .wrap_err("failed to serialize maps cache")
+            .map(Some)
+    }
+    /// writes the json from [Self::take_json] to [Self::FILE_NAME] inside `dir`
+    pub fn write_json(dir: &Dir, json: &str) -> Result<()> {
+        dir.write(Self::FILE_NAME, json)
+            .into_diagnostic()
+            .wrap_err("failed to write maps cache")
+    }
+    pub fn get(&self, id: u32) -> Option<&Map> {
+        self.maps.get(&id)
+    }
+    pub fn insert(&mut self, map: Map) {
+        self.maps.insert(map.id, map);
+        self.dirty = true;
+    }
+    /// returns the cached map or fetches it from the api
+    pub fn get_or_fetch(&mut self, client: &HttpClient, id: u32) -> Result<&Map> {
+        if !self.maps.contains_key(&id) {
+            let map = Map::get_id(client, "", &id).wrap_err("failed to fetch map from api")?;
+            self.insert(map);
+        }
+        Ok(&self.maps[&id])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queensdale() -> Map {
+        serde_json::from_str(include_str!("fixtures/map_15.json")).unwrap()
+    }
+
+    #[test]
+    fn deserialize_map() {
+        let map = queensdale();
+        assert_eq!(map.id, 15);
+        assert_eq!(map.continent_id, 1);
+        assert_eq!(map.map_rect, [[-43008.0, -27648.0], [43008.0, 30720.0]]);
+        assert_eq!(map.continent_rect, [[9856.0, 11648.0], [13440.0, 14080.0]]);
+    }
+
+    #[test]
+    fn map_corners_to_continent() {
+        let geometry = queensdale().geometry();
+        // south west corner of the map is the bottom left corner in continent
+        assert_eq!(
+            geometry.inches_to_continent(DVec2::new(-43008.0, -27648.0)),
+            DVec2::new(9856.0, 14080.0)
+        );
+        // north east corner is the top right corner
+        assert_eq!(
+            geometry.inches_to_continent(DVec2::new(43008.0, 30720.0)),
+            DVec2::new(13440.0, 11648.0)
+        );
+    }
+
+    #[test]
+    fn meters_continent_round_trip() {
+        let geometry = queensdale().geometry();
+        let position = Vec3::new(-250.0, 30.0, 120.0);
+        let continent = geometry.meters_to_continent(position);
+        let back = geometry.continent_to_meters(continent, position.y);
+        assert!((back - position).length() < 0.01);
+        // one continent unit is 24 inches
+        let east = geometry.meters_to_continent(position + Vec3::X * 24.0 / INCHES_PER_METER);
+        assert!((east - continent - vec2(1.0, 0.0)).length() < 0.01);
+    }
+
+    #[test]
+    fn anchor_matches_map_geometry() {
+        let geometry = queensdale().geometry();
+        let player = Vec3::new(-250.0, 30.0, 120.0);
+        let anchored = MapGeometry::from_anchor(player, geometry.meters_to_continent(player));
+        // 50 meters north east of the player
+        let position = player + Vec3::new(50.0, -10.0, 50.0);
+        let expected = geometry.meters_to_continent(position);
+        assert!((anchored.meters_to_continent(position) - expected).length() < 0.01);
+        assert!((anchored.continent_to_meters(expected, position.y) - position).length() < 0.01);
+    }
+
+    #[test]
+    fn cache_round_trip() {
+        let dir = std::env::temp_dir().join(format!("jokoapi_map_cache_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir_handle =
+            Dir::open_ambient_dir(dir.to_str().unwrap(), cap_std::ambient_authority()).unwrap();
+        let mut cache = MapCache::load(&dir_handle).unwrap();
+        assert!(cache.get(15).is_none());
+        cache.insert(queensdale());
+        cache.save(&dir_handle).unwrap();
+        let cache = MapCache::load(&dir_handle).unwrap();
+        assert_eq!(cache.get(15).unwrap().name, "Queensdale");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -4,7 +4,7 @@ use crate::prelude::*;
 
 #[bitflags]
 #[repr(u16)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mount {
     Raptor = 1 << 0,
     Springer = 1 << 1,
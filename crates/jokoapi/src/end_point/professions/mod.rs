@@ -0,0 +1,75 @@
+use std::str::FromStr;
+
+use crate::prelude::*;
+
+/// The core profession of a character. also used by markers to filter which professions they are active for
+#[bitflags]
+#[repr(u16)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profession {
+    Elementalist = 1 << 0,
+    Engineer = 1 << 1,
+    Guardian = 1 << 2,
+    Mesmer = 1 << 3,
+    Necromancer = 1 << 4,
+    Ranger = 1 << 5,
+    Revenant = 1 << 6,
+    Thief = 1 << 7,
+    Warrior = 1 << 8,
+}
+/// impl for mumble link
+impl Profession {
+    /// mumble link uses the ids of professions from the api. 0 means no profession (eg: character select)
+    pub fn try_from_mumble_link(value: u32) -> Option<Self> {
+        Some(match value {
+            1 => Self::Guardian,
+            2 => Self::Warrior,
+            3 => Self::Engineer,
+            4 => Self::Ranger,
+            5 => Self::Thief,
+            6 => Self::Elementalist,
+            7 => Self::Mesmer,
+            8 => Self::Necromancer,
+            9 => Self::Revenant,
+            _ => return None,
+        })
+    }
+}
+impl FromStr for Profession {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(match s {
+            "guardian" => Profession::Guardian,
+            "warrior" => Profession::Warrior,
+            "engineer" => Profession::Engineer,
+            "ranger" => Profession::Ranger,
+            "thief" => Profession::Thief,
+            "elementalist" => Profession::Elementalist,
+            "mesmer" => Profession::Mesmer,
+            "necromancer" => Profession::Necromancer,
+            "revenant" => Profession::Revenant,
+            _ => return Err("invalid profession"),
+        })
+    }
+}
+impl AsRef<str> for Profession {
+    fn as_ref(&self) -> &str {
+        match self {
+            Profession::Guardian => "guardian",
+            Profession::Warrior => "warrior",
+            Profession::Engineer => "engineer",
+            Profession::Ranger => "ranger",
+            Profession::Thief => "thief",
+            Profession::Elementalist => "elementalist",
+            Profession::Mesmer => "mesmer",
+            Profession::Necromancer => "necromancer",
+            Profession::Revenant => "revenant",
+        }
+    }
+}
+impl Display for Profession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
@@ -4,7 +4,7 @@ use crate::prelude::*;
 
 #[bitflags]
 #[repr(u8)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Race {
     ASURA = 1 << 0,
     CHARR = 1 << 2,
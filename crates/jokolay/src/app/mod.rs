@@ -8,8 +8,9 @@ use init::get_jokolay_dir;
 use jmf::MarkerManager;
 use joko_core::manager::{theme::ThemeManager, trace::JokolayTracingLayer};
 use joko_render::JokoRenderer;
-use jokolink::{MumbleChanges, MumbleManager};
+use jokolink::{events::MumbleEvent, MumbleManager};
 use miette::{Context, Result};
+use std::sync::mpsc::Receiver;
 use tracing::{error, info};
 #[allow(unused)]
 pub struct Jokolay {
@@ -17,6 +18,8 @@ pub struct Jokolay {
     jdir: Arc<Dir>,
     menu_panel: MenuPanel,
     mumble_manager: MumbleManager,
+    /// events from mumble manager. used to follow the gw2 window
+    mumble_events: Receiver<MumbleEvent>,
     marker_manager: MarkerManager,
     theme_manager: ThemeManager,
     joko_renderer: JokoRenderer,
@@ -25,8 +28,6 @@ pub struct Jokolay {
 }
 impl Jokolay {
     pub fn new(jdir: Arc<Dir>) -> Result<Self> {
-        let mumble =
-            MumbleManager::new("MumbleLink", None).wrap_err("failed to create mumble manager")?;
         let marker_manager =
             MarkerManager::new(&jdir).wrap_err("failed to create marker manager")?;
         let mut theme_manager =
@@ -52,9 +53,22 @@ impl Jokolay {
         });
         glfw_backend.window.set_floating(true);
         glfw_backend.window.set_decorated(false);
+        let mut mumble = match mumble_replay_arg() {
+            Some(path) => {
+                MumbleManager::new_replay(&path, jokolink::replay::ReplayOptions::from_env())
+            }
+            None => {
+                let names = mumble_link_names();
+                let names: Vec<&str> = names.iter().map(String::as_str).collect();
+                MumbleManager::new(&names, jokolay_window_id(&glfw_backend))
+            }
+        }
+        .wrap_err("failed to create mumble manager")?;
+        let mumble_events = mumble.subscribe();
         let joko_renderer = JokoRenderer::new(&mut glfw_backend, Default::default());
         Ok(Self {
             mumble_manager: mumble,
+            mumble_events,
             marker_manager,
             frame_stats: wm::WindowStatistics::new(glfw_backend.glfw.get_time() as _),
             joko_renderer,
@@ -75,6 +89,7 @@ impl Jokolay {
                 jdir: _,
                 menu_panel,
                 mumble_manager,
+                mumble_events,
                 marker_manager,
                 theme_manager,
                 joko_renderer,
@@ -134,7 +149,7 @@ impl Jokolay {
                 }
             };
             joko_renderer.tick(link.clone());
-            marker_manager.tick(&etx, latest_time, joko_renderer, &link);
+            marker_manager.tick(&etx, joko_renderer, &link);
             menu_panel.tick(&etx, link.clone().as_ref().map(|m| m.as_ref()));
 
             // do the gui stuff now
@@ -168,6 +183,11 @@ impl Jokolay {
                                     "Show Theme Manager",
                                 );
                                 ui.checkbox(&mut menu_panel.show_tracing_window, "Show Logs");
+                                ui.checkbox(
+                                    &mut joko_renderer.billboard_renderer.occlusion_hints,
+                                    "Occlusion Hints",
+                                )
+                                .on_hover_text("dim the markers between the camera and the player");
                                 if ui.button("exit").clicked() {
                                     info!("exiting jokolay");
                                     glfw_backend.window.set_should_close(true);
@@ -187,24 +207,21 @@ impl Jokolay {
 
             // end gui stuff
             // check if we need to change window position or size.
-            if let Some(link) = link.as_ref() {
-                if link.changes.contains(MumbleChanges::WindowPosition)
-                    || link.changes.contains(MumbleChanges::WindowSize)
-                {
-                    info!(
-                        ?link.client_pos, ?link.client_size,
-                        "resizing/repositioning to match gw2 window dimensions"
-                    );
-
-                    glfw_backend
-                        .window
-                        .set_pos(link.client_pos.x, link.client_pos.y);
-                    // if gw2 is in windowed fullscreen mode, then the size is full resolution of the screen/monitor.
-                    // But if we set that size, when you focus jokolay, the screen goes blank on win11 (some kind of fullscreen optimization maybe?)
-                    // so we remove a pixel from right/bottom edges. mostly indistinguishable, but makes sure that transparency works even in windowed fullscrene mode of gw2
-                    glfw_backend
-                        .window
-                        .set_size(link.client_size.x - 1, link.client_size.y - 1);
+            for event in mumble_events.try_iter() {
+                match event {
+                    MumbleEvent::WindowMoved(pos) => {
+                        info!(?pos, "repositioning to match gw2 window");
+                        glfw_backend.window.set_pos(pos.x, pos.y);
+                    }
+                    // gw2 window size is unknown (eg: raw link without x server). don't shrink jokolay to nothing
+                    MumbleEvent::WindowResized(size) if size.x > 1 && size.y > 1 => {
+                        info!(?size, "resizing to match gw2 window");
+                        // if gw2 is in windowed fullscreen mode, then the size is full resolution of the screen/monitor.
+                        // But if we set that size, when you focus jokolay, the screen goes blank on win11 (some kind of fullscreen optimization maybe?)
+                        // so we remove a pixel from right/bottom edges. mostly indistinguishable, but makes sure that transparency works even in windowed fullscrene mode of gw2
+                        glfw_backend.window.set_size(size.x - 1, size.y - 1);
+                    }
+                    _ => {}
                 }
             }
             etx.request_repaint();
@@ -223,9 +240,14 @@ impl Jokolay {
             }
 
             // if it doesn't require either keyboard or pointer, set passthrough to true
-            glfw_backend
-                .window
-                .set_mouse_passthrough(!(etx.wants_keyboard_input() || etx.wants_pointer_input()));
+            // while the player is typing in gw2's chat, all input belongs to gw2
+            let gw2_textbox_focused = link
+                .as_ref()
+                .map(|link| link.textbox_focused())
+                .unwrap_or_default();
+            glfw_backend.window.set_mouse_passthrough(
+                gw2_textbox_focused || !(etx.wants_keyboard_input() || etx.wants_pointer_input()),
+            );
             joko_renderer.render_egui(
                 etx.tessellate(shapes),
                 textures_delta,
@@ -237,6 +259,45 @@ impl Jokolay {
     }
 }
 
+/// comma separated list of mumble link names from `JOKOLAY_MUMBLE_LINKS` env, for multiboxing with `-mumble` option of gw2.
+/// defaults to just [jokolink::DEFAULT_MUMBLELINK_NAME]
+fn mumble_link_names() -> Vec<String> {
+    let names: Vec<String> = std::env::var("JOKOLAY_MUMBLE_LINKS")
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect();
+    if names.is_empty() {
+        vec![jokolink::DEFAULT_MUMBLELINK_NAME.to_string()]
+    } else {
+        names
+    }
+}
+/// `--mumble-replay <path>` plays back a mumble recording instead of the live link. same as the `JOKOLINK_REPLAY` env
+fn mumble_replay_arg() -> Option<std::path::PathBuf> {
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--mumble-replay" {
+            return args.next().map(Into::into);
+        }
+    }
+    None
+}
+/// x11 window id of jokolay window, so that jokolink can make it transient for gw2. None on wayland/windows
+fn jokolay_window_id(glfw_backend: &GlfwBackend) -> Option<u32> {
+    #[cfg(all(target_os = "linux", not(feature = "wayland")))]
+    {
+        let xid = glfw_backend.window.get_x11_window() as usize as u32;
+        (xid != 0).then_some(xid)
+    }
+    #[cfg(not(all(target_os = "linux", not(feature = "wayland"))))]
+    {
+        let _ = glfw_backend;
+        None
+    }
+}
 pub fn start_jokolay() {
     let jdir = match get_jokolay_dir() {
         Ok(jdir) => jdir,
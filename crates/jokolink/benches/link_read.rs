@@ -0,0 +1,160 @@
+//! compares the render thread cost of reading the link from the shm file every frame against
+//! reading it from the slot published by the inotify watcher / poll thread.
+//! a writer thread updates the file every 5ms like the jokolink dll does, so the background readers have some work to do.
+//! the background readers still read the file, but on their own thread and only as often as the link (or the poll interval) changes
+//! instead of every frame. so, the difference is the cpu time taken away from the render loop.
+//! criterion only times the render thread, so `cpu_usage` also measures the cpu time of the whole process (including the background threads)
+//! while running a 144 fps "render loop" for a fixed duration in each mode.
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use criterion::{black_box, Criterion};
+    use jokolink::ctypes::{CMumbleLink, C_MUMBLE_LINK_SIZE_FULL};
+    use jokolink::linux::{MumbleLinuxImpl, ReadMode};
+    use jokolink::MumbleBackend;
+    use std::io::Write;
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    /// how long each mode runs in [cpu_usage]
+    const CPU_WINDOW: Duration = Duration::from_secs(3);
+    const FRAME_TIME: Duration = Duration::from_micros(1_000_000 / 144);
+
+    fn write_link(path: &Path, ui_tick: u32) {
+        let link = CMumbleLink {
+            ui_tick,
+            ..Default::default()
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &link as *const CMumbleLink as *const u8,
+                C_MUMBLE_LINK_SIZE_FULL,
+            )
+        };
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .unwrap()
+            .write_all(bytes)
+            .unwrap();
+    }
+
+    const MODES: [(&str, ReadMode); 3] = [
+        ("per_frame", ReadMode::PerFrame),
+        ("inotify", ReadMode::Inotify),
+        ("poll_5ms", ReadMode::Poll(Duration::from_millis(5))),
+    ];
+
+    /// a link file which is updated every 5ms like the jokolink dll does. removed on drop
+    struct FakeGame {
+        path: PathBuf,
+        stop: Arc<AtomicBool>,
+        writer: Option<std::thread::JoinHandle<()>>,
+    }
+
+    impl FakeGame {
+        fn start() -> Self {
+            let path: PathBuf = std::env::temp_dir()
+                .join(format!("jokolink_bench_{}_MumbleLink", std::process::id()));
+            write_link(&path, 1);
+            let stop = Arc::new(AtomicBool::new(false));
+            let writer = std::thread::spawn({
+                let stop = stop.clone();
+                let path = path.clone();
+                move || {
+                    let mut ui_tick = 1;
+                    while !stop.load(Ordering::Relaxed) {
+                        ui_tick += 1;
+                        write_link(&path, ui_tick);
+                        std::thread::sleep(Duration::from_millis(5));
+                    }
+                }
+            });
+            Self {
+                path,
+                stop,
+                writer: Some(writer),
+            }
+        }
+    }
+
+    impl Drop for FakeGame {
+        fn drop(&mut self) {
+            self.stop.store(true, Ordering::Relaxed);
+            if let Some(writer) = self.writer.take() {
+                writer.join().unwrap();
+            }
+            std::fs::remove_file(&self.path).unwrap();
+        }
+    }
+
+    /// user + system cpu time of all threads of this process so far
+    fn process_cpu_time() -> Duration {
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) }, 0);
+        let to_duration = |time: libc::timeval| {
+            Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+        };
+        to_duration(usage.ru_utime) + to_duration(usage.ru_stime)
+    }
+
+    /// runs `frame` at 144 fps for [CPU_WINDOW] and returns the cpu time used by the process in that window
+    fn cpu_time_of(mut frame: impl FnMut()) -> Duration {
+        let start = Instant::now();
+        let cpu_start = process_cpu_time();
+        let mut next_frame = start;
+        while start.elapsed() < CPU_WINDOW {
+            frame();
+            next_frame += FRAME_TIME;
+            std::thread::sleep(next_frame.saturating_duration_since(Instant::now()));
+        }
+        process_cpu_time() - cpu_start
+    }
+
+    pub fn reads(c: &mut Criterion) {
+        let game = FakeGame::start();
+        let mut group = c.benchmark_group("link_read");
+        for (name, mode) in MODES {
+            let mut backend = MumbleLinuxImpl::with_mode(&game.path, mode).unwrap();
+            group.bench_function(name, |b| {
+                b.iter(|| {
+                    backend.tick().unwrap();
+                    black_box(backend.get_cmumble_link())
+                })
+            });
+        }
+        group.finish();
+    }
+
+    /// the render thread cost is only part of the story. the background readers also use cpu on their own threads.
+    /// The fake game's writer thread is measured alone first, so that it can be subtracted from the modes.
+    pub fn cpu_usage(_: &mut Criterion) {
+        let game = FakeGame::start();
+        let writer_only = cpu_time_of(|| {});
+        println!("link_read/cpu writer_only: {writer_only:?} in {CPU_WINDOW:?}");
+        for (name, mode) in MODES {
+            let mut backend = MumbleLinuxImpl::with_mode(&game.path, mode).unwrap();
+            let cpu_time = cpu_time_of(|| {
+                backend.tick().unwrap();
+                black_box(backend.get_cmumble_link());
+            });
+            let reader = cpu_time.saturating_sub(writer_only);
+            println!(
+                "link_read/cpu {name}: {reader:?} in {CPU_WINDOW:?} ({:.3}% of a core)",
+                reader.as_secs_f64() / CPU_WINDOW.as_secs_f64() * 100.0
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+criterion::criterion_group!(benches, linux::reads, linux::cpu_usage);
+#[cfg(target_os = "linux")]
+criterion::criterion_main!(benches);
+
+#[cfg(not(target_os = "linux"))]
+fn main() {}
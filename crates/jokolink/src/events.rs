@@ -0,0 +1,116 @@
+//! Typed events derived from the changes between two consecutive links.
+//! Subscribe with [crate::MumbleManager::subscribe] instead of diffing [crate::MumbleLink::changes] by hand.
+
+use glam::IVec2;
+use jokoapi::end_point::mounts::Mount;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use crate::{MumbleChanges, MumbleLink};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MumbleEvent {
+    /// gw2 started (or came back from character select/loading screen) and we got the first valid link
+    LinkAlive,
+    /// gw2 closed, crashed or is in a state without valid link data
+    LinkDied,
+    /// map id 0 means there was no map before (link just came alive)
+    MapChanged {
+        from: u32,
+        to: u32,
+    },
+    CharacterChanged {
+        from: String,
+        to: String,
+    },
+    MountChanged {
+        from: Option<Mount>,
+        to: Option<Mount>,
+    },
+    EnteredCombat,
+    LeftCombat,
+    /// new position of the gw2 client area in screen coordinates
+    WindowMoved(IVec2),
+    /// new size of the gw2 client area in screen coordinates
+    WindowResized(IVec2),
+}
+
+impl MumbleEvent {
+    /// pushes the events for the [MumbleLink::changes] of `link`. `previous` is the link that we sent events for last time,
+    /// and only provides the old values for the events. when the active client switches, all the changes are set.
+    /// So, the subscribers get the full state of the new client.
+    pub fn from_changes(previous: &MumbleLink, link: &MumbleLink, events: &mut Vec<MumbleEvent>) {
+        let changes = link.changes;
+        if changes.contains(MumbleChanges::Map) {
+            events.push(MumbleEvent::MapChanged {
+                from: previous.map_id,
+                to: link.map_id,
+            });
+        }
+        if changes.contains(MumbleChanges::Character) {
+            events.push(MumbleEvent::CharacterChanged {
+                from: previous.name.clone(),
+                to: link.name.clone(),
+            });
+        }
+        if changes.contains(MumbleChanges::Mount) {
+            events.push(MumbleEvent::MountChanged {
+                from: previous.mount,
+                to: link.mount,
+            });
+        }
+        if changes.contains(MumbleChanges::Combat) {
+            events.push(if link.in_combat() {
+                MumbleEvent::EnteredCombat
+            } else {
+                MumbleEvent::LeftCombat
+            });
+        }
+        if changes.contains(MumbleChanges::WindowPosition) {
+            events.push(MumbleEvent::WindowMoved(link.client_pos));
+        }
+        if changes.contains(MumbleChanges::WindowSize) {
+            events.push(MumbleEvent::WindowResized(link.client_size));
+        }
+    }
+}
+
+/// Keeps the latest link around to diff with the next one, and sends the events to all the subscribers
+#[derive(Default)]
+pub(crate) struct MumbleEventBus {
+    previous: Option<std::sync::Arc<MumbleLink>>,
+    subscribers: Vec<Sender<MumbleEvent>>,
+    /// reused every frame
+    events: Vec<MumbleEvent>,
+}
+
+impl MumbleEventBus {
+    pub fn subscribe(&mut self) -> Receiver<MumbleEvent> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+    /// `link` is what the manager returned this frame. None means that there's no valid link
+    pub fn tick(&mut self, link: Option<&std::sync::Arc<MumbleLink>>) {
+        self.events.clear();
+        match (self.previous.as_ref(), link) {
+            (None, Some(new)) => {
+                self.events.push(MumbleEvent::LinkAlive);
+                MumbleEvent::from_changes(&MumbleLink::default(), new, &mut self.events);
+            }
+            (Some(old), Some(new)) => MumbleEvent::from_changes(old, new, &mut self.events),
+            (Some(_), None) => self.events.push(MumbleEvent::LinkDied),
+            (None, None) => {}
+        }
+        self.previous = link.cloned();
+        if self.events.is_empty() {
+            return;
+        }
+        // drop the subscribers whose receivers are gone
+        let events = &self.events;
+        self.subscribers.retain(|subscriber| {
+            events
+                .iter()
+                .all(|event| subscriber.send(event.clone()).is_ok())
+        });
+    }
+}
@@ -8,15 +8,21 @@
 //! along with mumblelink data, it also copies the x11 window id of gw2. you can use this to get the size of gw2 window.
 //!
 
+pub mod events;
 mod mumble;
+pub mod replay;
+pub mod smoothing;
+pub mod synthetic;
 use egui::DragValue;
 use enumflags2::BitFlags;
 use glam::IVec2;
 use jokoapi::end_point::mounts::Mount;
-use miette::{IntoDiagnostic, Result, WrapErr};
+use miette::{bail, IntoDiagnostic, Result, WrapErr};
 pub use mumble::*;
+use replay::{MumbleRecorder, MumbleReplay, ReplayOptions};
 use serde_json::from_str;
-use std::sync::Arc;
+use smoothing::{MumbleSmoother, SmoothingOptions};
+use std::{path::Path, sync::Arc, time::Instant};
 use tracing::error;
 
 /// The default mumble link name. can only be changed by passing the `-mumble` options to gw2 for multiboxing
@@ -39,23 +45,204 @@ use win::MumbleWinImpl as MumblePlatformImpl;
 /// if any of the changed this frame, it will set the relevant changed flags so that plugins
 /// or other parts of program which care can run the relevant code.
 pub struct MumbleManager {
+    /// the mumble links that we are watching. one per gw2 client when multiboxing
+    sources: Vec<MumbleSource>,
+    /// index of the source which belongs to the focused gw2 client
+    active: usize,
+    /// records every new link of the active source into a file when enabled with [replay::RECORD_ENV]
+    recorder: Option<MumbleRecorder>,
+    /// sends the typed events to the subscribers
+    event_bus: events::MumbleEventBus,
+    /// smooths the positions of the link between gw2 ticks. enabled with [smoothing::SMOOTHING_ENV] or the gui
+    smoother: Option<MumbleSmoother>,
+    /// number of ticks so far. used to know which source updated most recently
+    frame: u64,
+}
+/// A source of raw mumble link data. implemented by the platform backends, [MumbleReplay] and [synthetic::SyntheticMumble]
+pub trait MumbleBackend {
+    /// called once every frame before getting the link
+    fn tick(&mut self) -> Result<()>;
+    /// whether gw2 is still running
+    fn is_alive(&self) -> bool;
+    /// latest link data. zeroed if not alive
+    fn get_cmumble_link(&mut self) -> ctypes::CMumbleLink;
+    /// any backend specific controls to show in the mumble manager window
+    fn gui(&mut self, _ui: &mut egui::Ui) {}
+}
+/// A single mumble link (by name) and the latest data we got from it
+struct MumbleSource {
+    /// the name of the link. [DEFAULT_MUMBLELINK_NAME] or whatever was passed to gw2 with `-mumble`
+    name: String,
     /// This abstracts over the windows and linux impl of mumble link functionality.
     /// we use this to get the latest mumble link and latest window dimensions of the current mumble link
-    backend: MumblePlatformImpl,
+    backend: Box<dyn MumbleBackend>,
+    /// raw link of this frame. kept for the recorder
+    cml: ctypes::CMumbleLink,
     /// latest mumble link
     link: Arc<MumbleLink>,
+    /// the [MumbleManager::frame] in which ui_tick of this link last changed
+    ui_tick_advanced_at: u64,
+    /// whether the last tick was an error. so that we don't log the errors of the inactive links every frame
+    failing: bool,
 }
 impl MumbleManager {
-    pub fn new(name: &str, _jokolay_window_id: Option<u32>) -> Result<Self> {
-        let backend = MumblePlatformImpl::new(name)?;
+    /// watches the mumble links with the given names. The first name is the active one until some gw2 client gets focus.
+    /// If [replay::REPLAY_ENV] is set, the recording at that path is replayed instead of using the live mumble links.
+    /// `jokolay_window_id` is the x11 window id of jokolay, which is made transient for the gw2 window on linux (see [linux::x11::X11_ENV])
+    pub fn new(names: &[&str], jokolay_window_id: Option<u32>) -> Result<Self> {
+        if let Ok(path) = std::env::var(replay::REPLAY_ENV) {
+            return Self::new_replay(Path::new(&path), ReplayOptions::from_env());
+        }
+        let mut backends: Vec<(String, Box<dyn MumbleBackend>)> = vec![];
+        for name in names {
+            let backend = MumblePlatformImpl::new(name)
+                .wrap_err_with(|| format!("failed to create mumble backend for {name}"))?;
+            #[cfg(target_os = "linux")]
+            let backend = backend.with_x11(linux::x11::X11Options::from_env(), jokolay_window_id);
+            #[cfg(not(target_os = "linux"))]
+            let _ = jokolay_window_id;
+            backends.push((name.to_string(), Box::new(backend)));
+        }
+        Self::with_backends(backends)
+    }
+    /// plays back the recording at `path` instead of reading the live mumble link
+    pub fn new_replay(path: &Path, options: ReplayOptions) -> Result<Self> {
+        let backend = MumbleReplay::open(path, options)?;
+        Self::with_backend(Box::new(backend))
+    }
+    /// uses a custom backend instead of the live mumble link
+    pub fn with_backend(backend: Box<dyn MumbleBackend>) -> Result<Self> {
+        Self::with_backends(vec![(DEFAULT_MUMBLELINK_NAME.to_string(), backend)])
+    }
+    /// uses custom backends with their link names instead of the live mumble links
+    pub fn with_backends(backends: Vec<(String, Box<dyn MumbleBackend>)>) -> Result<Self> {
+        if backends.is_empty() {
+            bail!("no mumble link names to watch");
+        }
+        let recorder = match std::env::var(replay::RECORD_ENV) {
+            Ok(path) => Some(MumbleRecorder::create(Path::new(&path))?),
+            Err(_) => None,
+        };
         Ok(Self {
-            backend,
-            link: Arc::new(Default::default()),
+            sources: backends
+                .into_iter()
+                .map(|(name, backend)| MumbleSource {
+                    name,
+                    backend,
+                    cml: Default::default(),
+                    link: Arc::new(Default::default()),
+                    ui_tick_advanced_at: 0,
+                    failing: false,
+                })
+                .collect(),
+            active: 0,
+            recorder,
+            event_bus: Default::default(),
+            smoother: SmoothingOptions::from_env().map(MumbleSmoother::new),
+            frame: 0,
         })
     }
+    /// name of the link which belongs to the focused gw2 client
+    pub fn active_link_name(&self) -> &str {
+        &self.sources[self.active].name
+    }
+    /// the receiver gets [events::MumbleEvent]s every frame that something changes. just drop the receiver to unsubscribe
+    pub fn subscribe(&mut self) -> std::sync::mpsc::Receiver<events::MumbleEvent> {
+        self.event_bus.subscribe()
+    }
+    /// None disables smoothing, and the links will have the positions exactly as gw2 wrote them
+    pub fn set_smoothing(&mut self, options: Option<SmoothingOptions>) {
+        self.smoother = options.map(MumbleSmoother::new);
+    }
+    /// ticks all the links and returns the link of the focused gw2 client.
+    /// when focus moves to a different client, all the change flags are set as it is basically a different game.
     pub fn tick(&mut self) -> Result<Option<Arc<MumbleLink>>> {
+        let mut result = self.tick_sources();
+        if let Ok(link) = result.as_mut() {
+            if let Some(smoother) = self.smoother.as_mut() {
+                match link {
+                    Some(link) => smoother.smooth(Arc::make_mut(link), Instant::now()),
+                    None => smoother.reset(),
+                }
+            }
+            self.event_bus.tick(link.as_ref());
+        }
+        result
+    }
+    fn tick_sources(&mut self) -> Result<Option<Arc<MumbleLink>>> {
+        self.frame += 1;
+        let frame = self.frame;
+        let mut results: Vec<_> = self
+            .sources
+            .iter_mut()
+            .map(|source| {
+                let result = source.tick();
+                if matches!(&result, Ok(Some(link)) if link.changes.contains(MumbleChanges::UiTick))
+                {
+                    source.ui_tick_advanced_at = frame;
+                }
+                result
+            })
+            .collect();
+        let previous_active = self.active;
+        // a frozen client keeps its stale GameHasFocus bit until it is considered dead. So, if multiple clients claim focus,
+        // pick the one whose ui_tick changed most recently. on a tie, stay with the active client instead of flickering.
+        let focused = results
+            .iter()
+            .enumerate()
+            .filter(|(_, result)| matches!(result, Ok(Some(link)) if link.game_has_focus()))
+            .max_by_key(|(index, _)| {
+                (
+                    self.sources[*index].ui_tick_advanced_at,
+                    *index == previous_active,
+                )
+            })
+            .map(|(index, _)| index);
+        if let Some(focused) = focused {
+            self.active = focused;
+        } else if !matches!(results[self.active], Ok(Some(_))) {
+            // the active client is gone. fallback to any client that is still alive
+            if let Some(alive) = results.iter().position(|r| matches!(r, Ok(Some(_)))) {
+                self.active = alive;
+            }
+        }
+        // the error of the active link is returned to the caller
+        for (index, (result, source)) in results.iter().zip(self.sources.iter_mut()).enumerate() {
+            if index != self.active && !source.failing {
+                if let Err(e) = result {
+                    error!(?e, name = source.name, "mumble link tick error");
+                }
+            }
+            source.failing = result.is_err();
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.record(&self.sources[self.active].cml) {
+                error!(?e, "failed to record mumble link. stopping the recording");
+                self.recorder = None;
+            }
+        }
+        let result = results.swap_remove(self.active);
+        if previous_active == self.active {
+            return result;
+        }
+        tracing::info!(
+            from = self.sources[previous_active].name,
+            to = self.sources[self.active].name,
+            pid = self.sources[self.active].cml.context.process_id,
+            "switching active mumble link"
+        );
+        Ok(result?.map(|link| {
+            let mut link = link.as_ref().clone();
+            link.changes = BitFlags::all();
+            Arc::new(link)
+        }))
+    }
+}
+impl MumbleSource {
+    fn tick(&mut self) -> Result<Option<Arc<MumbleLink>>> {
+        self.cml = Default::default();
         if let Err(e) = self.backend.tick() {
-            error!(?e, "mumble backend tick error");
+            error!(?e, name = self.name, "mumble backend tick error");
             return Ok(None);
         }
 
@@ -68,6 +255,7 @@ impl MumbleManager {
         }
         // backend is alive and tick is successful. time to get link
         let cml: ctypes::CMumbleLink = self.backend.get_cmumble_link();
+        self.cml = cml;
         if cml.ui_tick == 0 && self.link.ui_tick != 0 {
             self.link = Arc::new(Default::default());
         }
@@ -102,6 +290,31 @@ impl MumbleManager {
         if self.link.name != identity.name {
             changes.insert(MumbleChanges::Character);
         }
+        let profession = identity.get_profession();
+        if self.link.profession != profession {
+            changes.insert(MumbleChanges::Profession);
+        }
+        let spec = identity.get_spec();
+        if self.link.spec != spec {
+            changes.insert(MumbleChanges::Spec);
+        }
+        let mount = Mount::try_from_mumble_link(cml.context.mount_index);
+        if self.link.mount != mount {
+            changes.insert(MumbleChanges::Mount);
+        }
+        // unknown bits are ignored, in case gw2 adds more states in future
+        let ui_state = BitFlags::<UIState>::from_bits_truncate(cml.context.ui_state);
+        let toggled = self.link.ui_state ^ ui_state;
+        for (state, change) in [
+            (UIState::IsMapOpen, MumbleChanges::MapOpen),
+            (UIState::IsInCombat, MumbleChanges::Combat),
+            (UIState::GameHasFocus, MumbleChanges::GameFocus),
+            (UIState::TextboxFocus, MumbleChanges::TextboxFocus),
+        ] {
+            if toggled.contains(state) {
+                changes.insert(change);
+            }
+        }
         if self.link.map_id != cml.context.map_id {
             changes.insert(MumbleChanges::Map);
         }
@@ -142,6 +355,12 @@ impl MumbleManager {
             f_avatar_front: cml.f_avatar_front.into(),
             cam_pos: cml.f_camera_position.into(),
             f_camera_front: cml.f_camera_front.into(),
+            profession,
+            spec,
+            race: identity.get_race(),
+            world_id: identity.world_id,
+            team_color_id: identity.team_color_id,
+            commander: identity.commander,
             name: identity.name,
             map_id: cml.context.map_id,
             fov: identity.fov,
@@ -160,7 +379,7 @@ impl MumbleManager {
             shard_id: cml.context.shard_id,
             instance: cml.context.instance,
             build_id: cml.context.build_id,
-            ui_state: cml.context.ui_state,
+            ui_state,
             compass_width: cml.context.compass_width,
             compass_height: cml.context.compass_height,
             compass_rotation: cml.context.compass_rotation,
@@ -170,7 +389,8 @@ impl MumbleManager {
             map_center_y: cml.context.map_center_y,
             map_scale: cml.context.map_scale,
             process_id: cml.context.process_id,
-            mount: Mount::try_from_mumble_link(cml.context.mount_index),
+            mount,
+            link_name: self.name.clone(),
         });
         self.link = link.clone();
         Ok(if self.link.ui_tick == 0 {
@@ -179,14 +399,39 @@ impl MumbleManager {
             Some(link)
         })
     }
+}
+impl MumbleManager {
     pub fn gui(&mut self, etx: &egui::Context, open: &mut bool) {
         egui::Window::new("Mumble Manager")
             .open(open)
             .show(etx, |ui| {
-                if self.link.ui_tick == 0 {
+                if self.sources.len() > 1 {
+                    ui.horizontal(|ui| {
+                        for (index, source) in self.sources.iter().enumerate() {
+                            // only focusing the gw2 window can change the active link, so this is just informative
+                            let _ = ui.selectable_label(
+                                index == self.active,
+                                format!("{} ({})", source.name, source.link.name),
+                            );
+                        }
+                    });
+                }
+                let source = &mut self.sources[self.active];
+                source.backend.gui(ui);
+                if let Some(recorder) = self.recorder.as_ref() {
+                    ui.label(format!("recorded frames: {}", recorder.frames()));
+                }
+                let mut smoothing = self.smoother.is_some();
+                if ui
+                    .checkbox(&mut smoothing, "smooth positions between gw2 ticks")
+                    .changed()
+                {
+                    self.smoother = smoothing.then(|| MumbleSmoother::new(Default::default()));
+                }
+                if source.link.ui_tick == 0 {
                     ui.label("Mumble is not initialized");
                 } else {
-                    let link: MumbleLink = self.link.as_ref().clone();
+                    let link: MumbleLink = source.link.as_ref().clone();
                     mumble_ui(ui, link);
                 }
             });
@@ -241,6 +486,24 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             ui.label("character");
             ui.label(&link.name);
             ui.end_row();
+            ui.label("profession");
+            ui.label(format!("{:?}", link.profession));
+            ui.end_row();
+            ui.label("specialization");
+            ui.label(format!("{:?}", link.spec));
+            ui.end_row();
+            ui.label("race");
+            ui.label(format!("{:?}", link.race));
+            ui.end_row();
+            ui.label("world id");
+            ui.add(DragValue::new(&mut link.world_id));
+            ui.end_row();
+            ui.label("team color");
+            ui.add(DragValue::new(&mut link.team_color_id));
+            ui.end_row();
+            ui.label("commander");
+            ui.checkbox(&mut link.commander, "");
+            ui.end_row();
             ui.label("map id");
             ui.add(DragValue::new(&mut link.map_id));
             ui.end_row();
@@ -256,6 +519,9 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             ui.label("shard id");
             ui.add(DragValue::new(&mut link.shard_id));
             ui.end_row();
+            ui.label("ui state");
+            ui.label(format!("{:?}", link.ui_state));
+            ui.end_row();
             ui.label("mount");
             ui.label(format!("{:?}", link.mount));
             ui.end_row();
@@ -304,3 +570,296 @@ fn mumble_ui(ui: &mut egui::Ui, mut link: MumbleLink) {
             // ui.end_row();
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::{vec3, Vec3};
+    use jokoapi::end_point::{
+        professions::Profession, races::Race, specializations::Specialization,
+    };
+    use synthetic::{SyntheticMumble, SyntheticStep};
+
+    fn manager(synthetic: SyntheticMumble) -> MumbleManager {
+        MumbleManager::with_backend(Box::new(synthetic)).unwrap()
+    }
+
+    #[test]
+    fn first_tick_has_all_changes() {
+        let mut manager = manager(SyntheticMumble::walk(&[Vec3::ZERO, Vec3::X], 1));
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.ui_tick, 1);
+        assert_eq!(link.map_id, 15);
+        assert_eq!(link.name, "Synthetic Character");
+        assert_eq!(link.uisz, UISize::Normal);
+        assert_eq!(link.client_size, IVec2::new(1920, 1080));
+        // window is at the origin, which is the same position as the default link
+        assert_eq!(
+            link.changes,
+            MumbleChanges::UiTick
+                | MumbleChanges::Map
+                | MumbleChanges::Character
+                | MumbleChanges::WindowSize
+                | MumbleChanges::GameFocus
+        );
+        // just walking only changes the tick
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.player_pos, Vec3::X);
+        assert_eq!(link.changes, MumbleChanges::UiTick);
+    }
+
+    #[test]
+    fn detects_map_character_and_window_changes() {
+        let mut manager = manager(SyntheticMumble::new([
+            SyntheticStep::MoveTo(vec3(1.0, 2.0, 3.0)),
+            SyntheticStep::ChangeMap(50),
+            SyntheticStep::ChangeCharacter("Other Character".to_string()),
+            SyntheticStep::ClientPosSize([0, 0, 800, 600]),
+            SyntheticStep::Freeze,
+        ]));
+        manager.tick().unwrap();
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.map_id, 50);
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Map);
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.name, "Other Character");
+        assert_eq!(
+            link.changes,
+            MumbleChanges::UiTick | MumbleChanges::Character
+        );
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(
+            link.changes,
+            MumbleChanges::UiTick | MumbleChanges::WindowSize
+        );
+        let link = manager.tick().unwrap().unwrap();
+        assert!(link.changes.is_empty());
+    }
+
+    #[test]
+    fn invalid_identity_is_an_error() {
+        let mut manager = manager(SyntheticMumble::new([
+            SyntheticStep::RawIdentity("{\"name\": \"broken".to_string()),
+            SyntheticStep::RawIdentity(
+                serde_json::to_string(&ctypes::CIdentity {
+                    uisz: 7,
+                    ..Default::default()
+                })
+                .unwrap(),
+            ),
+        ]));
+        assert!(manager.tick().is_err());
+        // valid json, but invalid ui size
+        assert!(manager.tick().is_err());
+    }
+
+    #[test]
+    fn dead_link_is_reset() {
+        let mut manager = manager(SyntheticMumble::new([
+            SyntheticStep::MoveTo(Vec3::ZERO),
+            SyntheticStep::Close,
+            SyntheticStep::Start,
+        ]));
+        assert!(manager.tick().unwrap().is_some());
+        assert_eq!(manager.sources[0].link.ui_tick, 1);
+        assert!(manager.tick().unwrap().is_none());
+        assert_eq!(manager.sources[0].link.ui_tick, 0);
+        // gw2 started again, so everything is a change again
+        let link = manager.tick().unwrap().unwrap();
+        assert!(link
+            .changes
+            .contains(MumbleChanges::Map | MumbleChanges::Character));
+    }
+
+    #[test]
+    fn follows_the_focused_client() {
+        let first = SyntheticMumble::new([
+            SyntheticStep::MoveTo(Vec3::ZERO),
+            SyntheticStep::Focus(false),
+            SyntheticStep::MoveTo(Vec3::X),
+        ]);
+        let mut second = SyntheticMumble::new([
+            SyntheticStep::Focus(false),
+            SyntheticStep::Focus(true),
+            SyntheticStep::Close,
+        ]);
+        second.identity.name = "Second Character".to_string();
+        second.link.context.map_id = 50;
+        let mut manager = MumbleManager::with_backends(vec![
+            ("MumbleLink".to_string(), Box::new(first)),
+            ("MumbleLink2".to_string(), Box::new(second)),
+        ])
+        .unwrap();
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.link_name, "MumbleLink");
+        // focus moves to the second client
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(manager.active_link_name(), "MumbleLink2");
+        assert_eq!(link.name, "Second Character");
+        assert_eq!(link.map_id, 50);
+        assert_eq!(link.changes, BitFlags::all());
+        // second client is closed, so we fallback to the first even though it is not focused
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.link_name, "MumbleLink");
+        assert_eq!(link.player_pos, Vec3::X);
+    }
+
+    #[test]
+    fn frozen_client_loses_focus() {
+        // both clients claim focus, but the first one froze
+        let first = SyntheticMumble::new([
+            SyntheticStep::MoveTo(Vec3::ZERO),
+            SyntheticStep::Freeze,
+            SyntheticStep::Freeze,
+        ]);
+        let mut second = SyntheticMumble::new([
+            SyntheticStep::MoveTo(Vec3::ZERO),
+            SyntheticStep::MoveTo(Vec3::X),
+            SyntheticStep::Freeze,
+        ]);
+        second.identity.name = "Second Character".to_string();
+        let mut manager = MumbleManager::with_backends(vec![
+            ("MumbleLink".to_string(), Box::new(first)),
+            ("MumbleLink2".to_string(), Box::new(second)),
+        ])
+        .unwrap();
+        // both advanced. stay with the active one
+        manager.tick().unwrap().unwrap();
+        assert_eq!(manager.active_link_name(), "MumbleLink");
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(manager.active_link_name(), "MumbleLink2");
+        assert_eq!(link.name, "Second Character");
+        // second client didn't render a new frame yet, but it is still the most recent one
+        manager.tick().unwrap().unwrap();
+        assert_eq!(manager.active_link_name(), "MumbleLink2");
+    }
+
+    #[test]
+    fn identity_json_is_parsed() {
+        let mut manager = manager(SyntheticMumble::new([
+            SyntheticStep::RawIdentity(
+                r#"{"name":"Sylvari Ranger","profession":4,"spec":55,"race":4,"map_id":15,"world_id":268435505,"team_color_id":9,"commander":true,"map":15,"fov":0.873,"uisz":2}"#
+                    .to_string(),
+            ),
+            SyntheticStep::RawIdentity(
+                r#"{"name":"Sylvari Ranger","profession":7,"spec":0,"race":4,"map_id":15,"world_id":268435505,"team_color_id":0,"commander":false,"map":15,"fov":0.873,"uisz":2}"#
+                    .to_string(),
+            ),
+            SyntheticStep::Mount(5),
+        ]));
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.name, "Sylvari Ranger");
+        assert_eq!(link.profession, Some(Profession::Ranger));
+        assert_eq!(link.spec, Some(Specialization::Soulbeast));
+        assert_eq!(link.race, Some(Race::SYLVARI));
+        assert_eq!(link.world_id, 268435505);
+        assert_eq!(link.team_color_id, 9);
+        assert!(link.commander);
+        assert_eq!(link.uisz, UISize::Large);
+        assert!(link
+            .changes
+            .contains(MumbleChanges::Profession | MumbleChanges::Spec));
+        // same character after changing the profession and removing the elite spec. not really possible in game, but still
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.profession, Some(Profession::Mesmer));
+        assert_eq!(link.spec, None);
+        assert!(!link.commander);
+        assert_eq!(
+            link.changes,
+            MumbleChanges::UiTick | MumbleChanges::Profession | MumbleChanges::Spec
+        );
+        let link = manager.tick().unwrap().unwrap();
+        assert_eq!(link.mount, Some(Mount::Raptor));
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Mount);
+    }
+
+    #[test]
+    fn ui_state_toggles() {
+        let mut manager = manager(SyntheticMumble::new([
+            SyntheticStep::MoveTo(Vec3::ZERO),
+            SyntheticStep::UiState(UIState::IsMapOpen, true),
+            SyntheticStep::UiState(UIState::TextboxFocus, true),
+            SyntheticStep::UiState(UIState::IsInCombat, true),
+            SyntheticStep::UiState(UIState::IsMapOpen, false),
+        ]));
+        let link = manager.tick().unwrap().unwrap();
+        assert!(link.game_has_focus());
+        assert!(!link.is_map_open());
+        let link = manager.tick().unwrap().unwrap();
+        assert!(link.is_map_open());
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::MapOpen);
+        let link = manager.tick().unwrap().unwrap();
+        assert!(link.textbox_focused());
+        assert_eq!(
+            link.changes,
+            MumbleChanges::UiTick | MumbleChanges::TextboxFocus
+        );
+        let link = manager.tick().unwrap().unwrap();
+        assert!(link.in_combat());
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::Combat);
+        let link = manager.tick().unwrap().unwrap();
+        assert!(!link.is_map_open() && link.textbox_focused() && link.in_combat());
+        assert_eq!(link.changes, MumbleChanges::UiTick | MumbleChanges::MapOpen);
+    }
+
+    #[test]
+    fn events_are_sent_to_subscribers() {
+        use events::MumbleEvent;
+        let mut manager = manager(SyntheticMumble::new([
+            SyntheticStep::MoveTo(Vec3::ZERO),
+            SyntheticStep::ChangeMap(50),
+            SyntheticStep::Mount(5),
+            SyntheticStep::UiState(UIState::IsInCombat, true),
+            SyntheticStep::ClientPosSize([10, 20, 800, 600]),
+            SyntheticStep::ChangeCharacter("Other Character".to_string()),
+            SyntheticStep::Close,
+            SyntheticStep::Start,
+        ]));
+        let events = manager.subscribe();
+        let dropped = manager.subscribe();
+        drop(dropped);
+        let mut tick = || {
+            let _ = manager.tick();
+            events.try_iter().collect::<Vec<_>>()
+        };
+        assert_eq!(
+            tick(),
+            vec![
+                MumbleEvent::LinkAlive,
+                MumbleEvent::MapChanged { from: 0, to: 15 },
+                MumbleEvent::CharacterChanged {
+                    from: String::new(),
+                    to: "Synthetic Character".to_string()
+                },
+                MumbleEvent::WindowResized(IVec2::new(1920, 1080)),
+            ]
+        );
+        assert_eq!(tick(), vec![MumbleEvent::MapChanged { from: 15, to: 50 }]);
+        assert_eq!(
+            tick(),
+            vec![MumbleEvent::MountChanged {
+                from: None,
+                to: Some(Mount::Raptor)
+            }]
+        );
+        assert_eq!(tick(), vec![MumbleEvent::EnteredCombat]);
+        assert_eq!(
+            tick(),
+            vec![
+                MumbleEvent::WindowMoved(IVec2::new(10, 20)),
+                MumbleEvent::WindowResized(IVec2::new(800, 600))
+            ]
+        );
+        assert_eq!(
+            tick(),
+            vec![MumbleEvent::CharacterChanged {
+                from: "Synthetic Character".to_string(),
+                to: "Other Character".to_string()
+            }]
+        );
+        assert_eq!(tick(), vec![MumbleEvent::LinkDied]);
+        assert_eq!(tick()[0], MumbleEvent::LinkAlive);
+        // nothing changes
+        assert_eq!(tick(), vec![]);
+    }
+}
@@ -1,304 +1,525 @@
+mod slot;
+pub mod x11;
+
 use crate::ctypes::{CMumbleLink, C_MUMBLE_LINK_SIZE_FULL};
+use crate::MumbleBackend;
 use miette::{Context, IntoDiagnostic, Result};
+use notify::Watcher;
+use slot::LinkSlot;
 use std::fs::File;
 use std::io::{Read, Seek};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
-use tracing::info;
-// use x11rb::protocol::xproto::{change_property, intern_atom, AtomEnum, GetGeometryReply, PropMode};
-// use x11rb::rust_connection::ConnectError;
+use tracing::{error, info, warn};
+use x11::{X11Options, X11Tracker};
 
 pub use x11rb::rust_connection::RustConnection;
 
-/// This is the bak
+/// If the link doesn't update for this long, we assume that gw2 is closed
+const LIVENESS_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The layout of the data in the shm file. detected from the contents every tick, so that we can work with any bridge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkLayout {
+    /// written by the jokolink dll. has a timestamp, xid and gw2 window geometry after the context data of gw2
+    Jokolink,
+    /// just the raw mumble `LinkedMem` (5460 bytes) copied by other bridges, like the ones used by other linux overlays.
+    /// no timestamp or window geometry, so we check ui_tick progression for liveness
+    Raw,
+}
+
+/// env var to choose the [ReadMode] of the linux backend. `frame` (default), `inotify` or `poll:<milliseconds>` (eg: `poll:5`)
+pub const READ_MODE_ENV: &str = "JOKOLINK_READ_MODE";
+/// interval of [ReadMode::Poll] if `poll` is used without an interval. same as the default interval of jokolink dll
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How the link is read from the shm file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadMode {
+    /// rewind and read the whole file on the render thread in every tick
+    #[default]
+    PerFrame,
+    /// read the file on a helper thread only when inotify says that it was modified.
+    /// works with bridges which `write` into the file (like jokolink dll), but not the ones which `mmap` it.
+    Inotify,
+    /// read the file on a helper thread at this interval. works with any bridge
+    Poll(Duration),
+}
+
+impl ReadMode {
+    /// reads [READ_MODE_ENV]
+    pub fn from_env() -> Self {
+        match std::env::var(READ_MODE_ENV) {
+            Ok(mode) => Self::parse(&mode).unwrap_or_else(|| {
+                warn!(mode, "invalid mumble read mode. using per frame reads");
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+    fn parse(mode: &str) -> Option<Self> {
+        match mode.trim() {
+            "frame" => Some(Self::PerFrame),
+            "inotify" => Some(Self::Inotify),
+            "poll" => Some(Self::Poll(DEFAULT_POLL_INTERVAL)),
+            mode => mode
+                .strip_prefix("poll:")
+                .and_then(|millis| millis.parse().ok())
+                .map(|millis| Self::Poll(Duration::from_millis(millis))),
+        }
+    }
+}
+
+/// Where the render thread gets the link bytes from
+enum LinkSource {
+    File(File),
+    /// a helper thread (or the inotify watcher's thread) reads the file and publishes the bytes into the slot
+    Slot {
+        slot: Arc<LinkSlot>,
+        /// number of writes into the slot when we last read it. no need to look at the bytes again if it didn't change
+        version: u64,
+        _reader: BackgroundReader,
+    },
+}
+
+/// Keeps the helper thread alive. stops it when dropped
+enum BackgroundReader {
+    Watcher {
+        _watcher: notify::RecommendedWatcher,
+    },
+    Thread {
+        stop: Arc<AtomicBool>,
+        handle: Option<JoinHandle<()>>,
+    },
+}
+
+impl Drop for BackgroundReader {
+    fn drop(&mut self) {
+        if let BackgroundReader::Thread { stop, handle } = self {
+            stop.store(true, Ordering::Relaxed);
+            if let Some(handle) = handle.take() {
+                if handle.join().is_err() {
+                    error!("mumble link poll thread panicked");
+                }
+            }
+        }
+    }
+}
+
+/// This is the backend which reads the mumble link copied into `/dev/shm` by a bridge running inside wine
 pub struct MumbleLinuxImpl {
-    mfile: File,
+    source: LinkSource,
     link_buffer: LinkBuffer,
+    layout: LinkLayout,
     /// we basically use this as the ui_tick of mumblelink
     /// If this changed recently, it means jokolink is running (i.e. gw2 is running)
     previous_jokolink_timestamp: i128,
+    /// for the raw layout. the last ui_tick and when it changed
+    previous_ui_tick: u32,
+    ui_tick_changed_at: Option<Instant>,
+    /// raw layout has no window geometry. so, we assume that gw2 covers the whole screen. x, y, width, height.
+    /// queried once when the backend is created. None if we couldn't connect to x server
+    screen_pos_size: Option<[i32; 4]>,
+    /// finds the gw2 window with the xid written by jokolink. None if disabled or x11 is not available
+    x11: Option<X11Tracker>,
 }
 
 type LinkBuffer = Box<[u8; C_MUMBLE_LINK_SIZE_FULL]>;
 
 impl MumbleLinuxImpl {
+    /// uses the [ReadMode] from [READ_MODE_ENV]
     pub fn new(link_name: &str) -> Result<Self> {
-        let mumble_file_name = format!("/dev/shm/{link_name}");
-        info!("creating mumble file at {mumble_file_name}");
-        let mut mfile = File::options()
+        Self::with_mode(
+            Path::new(&format!("/dev/shm/{link_name}")),
+            ReadMode::from_env(),
+        )
+    }
+    /// reads the link from any file instead of `/dev/shm/link_name`
+    pub fn with_path(mumble_file_name: &Path) -> Result<Self> {
+        Self::with_mode(mumble_file_name, ReadMode::PerFrame)
+    }
+    pub fn with_mode(mumble_file_name: &Path, mode: ReadMode) -> Result<Self> {
+        info!("creating mumble file at {mumble_file_name:?}");
+        let mfile = File::options()
             .read(true)
             .write(true) // write/append is needed for the create flag
             .create(true)
-            .open(&mumble_file_name)
+            .truncate(false)
+            .open(mumble_file_name)
             .into_diagnostic()
             .wrap_err("failed to create mumble file")?;
-        let mut link_buffer = LinkBuffer::new([0u8; C_MUMBLE_LINK_SIZE_FULL]);
-        mfile.rewind().into_diagnostic()?;
-        mfile
-            .read(link_buffer.as_mut())
-            .into_diagnostic()
-            .wrap_err("failed to get link buffer from mfile")?;
-        let previous_jokolink_timestamp =
-            unsafe { CMumbleLink::get_timestamp(link_buffer.as_ptr() as _) };
-        Ok(MumbleLinuxImpl {
-            mfile,
-            link_buffer,
-            previous_jokolink_timestamp,
-        })
+        let source = match mode {
+            ReadMode::PerFrame => LinkSource::File(mfile),
+            ReadMode::Inotify => {
+                let slot = Arc::new(LinkSlot::default());
+                let mut reader = SlotWriter::new(mfile, slot.clone());
+                reader.update();
+                let mut watcher = notify::recommended_watcher(
+                    move |ev: notify::Result<notify::Event>| match ev {
+                        Ok(ev) => {
+                            if ev.kind.is_modify() {
+                                reader.update();
+                            }
+                        }
+                        Err(e) => error!(?e, "mumble link watcher error"),
+                    },
+                )
+                .into_diagnostic()
+                .wrap_err("failed to create mumble link watcher")?;
+                watcher
+                    .watch(mumble_file_name, notify::RecursiveMode::NonRecursive)
+                    .into_diagnostic()
+                    .wrap_err("failed to watch mumble link file")?;
+                LinkSource::Slot {
+                    slot,
+                    version: 0,
+                    _reader: BackgroundReader::Watcher { _watcher: watcher },
+                }
+            }
+            ReadMode::Poll(interval) => {
+                let slot = Arc::new(LinkSlot::default());
+                let mut reader = SlotWriter::new(mfile, slot.clone());
+                reader.update();
+                let stop = Arc::new(AtomicBool::new(false));
+                let handle = std::thread::Builder::new()
+                    .name("mumble link poll".to_string())
+                    .spawn({
+                        let stop = stop.clone();
+                        move || {
+                            while !stop.load(Ordering::Relaxed) {
+                                reader.update();
+                                std::thread::sleep(interval);
+                            }
+                        }
+                    })
+                    .into_diagnostic()
+                    .wrap_err("failed to spawn mumble link poll thread")?;
+                LinkSource::Slot {
+                    slot,
+                    version: 0,
+                    _reader: BackgroundReader::Thread {
+                        stop,
+                        handle: Some(handle),
+                    },
+                }
+            }
+        };
+        info!(?mode, "mumble link read mode");
+        let mut backend = MumbleLinuxImpl {
+            source,
+            link_buffer: LinkBuffer::new([0u8; C_MUMBLE_LINK_SIZE_FULL]),
+            layout: LinkLayout::Jokolink,
+            previous_jokolink_timestamp: 0,
+            previous_ui_tick: 0,
+            ui_tick_changed_at: None,
+            // connect to x server here instead of the first raw link on the render thread
+            screen_pos_size: Self::get_screen_pos_size(),
+            x11: None,
+        };
+        backend.read_link()?;
+        // a ui_tick from before we started doesn't mean that gw2 is alive
+        backend.ui_tick_changed_at = None;
+        Ok(backend)
     }
-    pub fn tick(&mut self) -> Result<()> {
-        self.mfile.rewind().into_diagnostic()?;
-        self.mfile
-            .read(self.link_buffer.as_mut())
-            .into_diagnostic()
-            .wrap_err("failed to get link buffer")?;
+    /// enables the x11 window discovery of gw2 window. `jokolay_window_id` is the xid of jokolay window, which is needed for `WM_TRANSIENT_FOR`.
+    /// does nothing on wayland or if we can't connect to the x server
+    pub fn with_x11(mut self, options: X11Options, jokolay_window_id: Option<u32>) -> Self {
+        self.x11 = X11Tracker::new(options, jokolay_window_id);
+        self
+    }
+    pub fn layout(&self) -> LinkLayout {
+        self.layout
+    }
+    fn read_link(&mut self) -> Result<()> {
+        match &mut self.source {
+            LinkSource::File(mfile) => read_file(mfile, &mut self.link_buffer)?,
+            LinkSource::Slot { slot, version, .. } => {
+                let latest = slot.read(&mut self.link_buffer);
+                if latest == *version {
+                    return Ok(());
+                }
+                *version = latest;
+            }
+        }
         self.previous_jokolink_timestamp =
             unsafe { CMumbleLink::get_timestamp(self.link_buffer.as_ptr() as _) };
+        let layout = if self.previous_jokolink_timestamp == 0 {
+            LinkLayout::Raw
+        } else {
+            LinkLayout::Jokolink
+        };
+        if layout != self.layout {
+            info!(?layout, "mumble link layout changed");
+            self.layout = layout;
+        }
+        let ui_tick =
+            unsafe { std::ptr::read_unaligned(self.link_buffer.as_ptr() as *const CMumbleLink) }
+                .ui_tick;
+        if ui_tick != self.previous_ui_tick {
+            self.previous_ui_tick = ui_tick;
+            self.ui_tick_changed_at = Some(Instant::now());
+        }
         Ok(())
     }
-    pub fn is_alive(&self) -> bool {
-        OffsetDateTime::now_utc().unix_timestamp_nanos() - self.previous_jokolink_timestamp
-            < std::time::Duration::from_secs(1).as_nanos() as i128
+    /// size of the default screen of x11. None if we can't connect to x server
+    fn get_screen_pos_size() -> Option<[i32; 4]> {
+        match RustConnection::connect(None) {
+            Ok((xc, screen_num)) => {
+                let screen = &x11rb::connection::Connection::setup(&xc).roots[screen_num];
+                Some([
+                    0,
+                    0,
+                    screen.width_in_pixels as i32,
+                    screen.height_in_pixels as i32,
+                ])
+            }
+            Err(e) => {
+                warn!(?e, "failed to connect to x server to get the screen size");
+                None
+            }
+        }
     }
-    pub fn get_cmumble_link(&self) -> CMumbleLink {
-        if self.is_alive() {
-            unsafe { std::ptr::read(self.link_buffer.as_ptr() as _) }
-        } else {
-            Default::default()
+}
+
+fn read_file(mfile: &mut File, link_buffer: &mut [u8; C_MUMBLE_LINK_SIZE_FULL]) -> Result<()> {
+    mfile.rewind().into_diagnostic()?;
+    link_buffer.fill(0);
+    mfile
+        .read(link_buffer)
+        .into_diagnostic()
+        .wrap_err("failed to get link buffer")?;
+    Ok(())
+}
+
+/// Reads the file and publishes it into the slot. lives on the helper thread
+struct SlotWriter {
+    mfile: File,
+    link_buffer: LinkBuffer,
+    slot: Arc<LinkSlot>,
+    /// so that we don't spam the logs 200 times per second
+    failing: bool,
+}
+
+impl SlotWriter {
+    fn new(mfile: File, slot: Arc<LinkSlot>) -> Self {
+        Self {
+            mfile,
+            link_buffer: LinkBuffer::new([0u8; C_MUMBLE_LINK_SIZE_FULL]),
+            slot,
+            failing: false,
+        }
+    }
+    fn update(&mut self) {
+        match read_file(&mut self.mfile, &mut self.link_buffer) {
+            Ok(_) => {
+                self.failing = false;
+                self.slot.write(&self.link_buffer);
+            }
+            Err(e) => {
+                if !self.failing {
+                    error!(?e, "failed to read mumble link file");
+                }
+                self.failing = true;
+            }
         }
     }
-    // pub fn set_transient_for(&self) -> Result<()> {
-    //     Ok(())
-    // Ok(self
-    //     .xc
-    //     .set_transient_for(xid_from_buffer(&self.link_buffer))?)
-    // }
 }
 
-// struct X11Connection {
-//     jokolay_window_id: u32,
-//     transient_for_atom: u32,
-//     // net_wm_pid_atom: u32,
-//     xc: RustConnection,
-// }
-// impl X11Connection {
-//     pub const WM_TRANSIENT_FOR: &'static str = "WM_TRANSIENT_FOR";
-//     // pub const NET_WM_PID: &'static str = "_NET_WM_PID";
-//     fn new(jokolay_window_id: u32) -> Result<Self, X11Error> {
-//         let (xc, _) = RustConnection::connect(None).expect("failed to create x11 connection");
-//         let transient_for_atom = intern_atom(&xc, true, Self::WM_TRANSIENT_FOR.as_bytes())
-//             .map_err(|e| X11Error::AtomQueryError {
-//                 source: e,
-//                 atom_str: Self::WM_TRANSIENT_FOR,
-//             })?
-//             .reply()
-//             .map_err(|e| X11Error::AtomReplyError {
-//                 source: e,
-//                 atom_str: Self::WM_TRANSIENT_FOR,
-//             })?
-//             .atom;
-//         // let net_wm_pid_atom = intern_atom(&xc, true, Self::NET_WM_PID.as_bytes())
-//         //     .map_err(|e| X11Error::AtomQueryError {
-//         //         source: e,
-//         //         atom_str: Self::NET_WM_PID,
-//         //     })?
-//         //     .reply()
-//         //     .map_err(|e| X11Error::AtomReplyError {
-//         //         source: e,
-//         //         atom_str: Self::NET_WM_PID,
-//         //     })?
-//         //     .atom;
+impl MumbleBackend for MumbleLinuxImpl {
+    fn tick(&mut self) -> Result<()> {
+        self.read_link()
+    }
+    fn is_alive(&self) -> bool {
+        match self.layout {
+            LinkLayout::Jokolink => {
+                OffsetDateTime::now_utc().unix_timestamp_nanos() - self.previous_jokolink_timestamp
+                    < LIVENESS_TIMEOUT.as_nanos() as i128
+            }
+            LinkLayout::Raw => {
+                self.previous_ui_tick != 0
+                    && self
+                        .ui_tick_changed_at
+                        .map(|changed_at| changed_at.elapsed() < LIVENESS_TIMEOUT)
+                        .unwrap_or_default()
+            }
+        }
+    }
+    fn get_cmumble_link(&mut self) -> CMumbleLink {
+        if !self.is_alive() {
+            return Default::default();
+        }
+        let mut link: CMumbleLink =
+            unsafe { std::ptr::read_unaligned(self.link_buffer.as_ptr() as _) };
+        if self.layout == LinkLayout::Raw && link.context.client_pos_size == [0; 4] {
+            // without a screen size, we leave the geometry alone and the window just stays where it is
+            if let Some(screen_pos_size) = self.screen_pos_size {
+                link.context.client_pos_size = screen_pos_size;
+            }
+        }
+        if self.layout == LinkLayout::Jokolink {
+            if let Some(x11) = self.x11.as_mut() {
+                x11.update(&mut link);
+            }
+        }
+        link
+    }
+    fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.label(format!("link layout: {:?}", self.layout));
+        let Some(x11) = self.x11.as_ref() else {
+            return;
+        };
+        ui.collapsing("x11", |ui| {
+            ui.label(format!("options: {:?}", x11.options));
+            ui.label(format!("jokolay window: {:?}", x11.jokolay_window_id));
+            ui.label(format!("transient for: {:?}", x11.transient_parent));
+            match x11.geometry {
+                Some(geometry) => {
+                    ui.label(format!("gw2 window: {:?}", geometry.pos_size));
+                    ui.label(format!("frame extents: {:?}", geometry.frame_extents));
+                }
+                None => {
+                    ui.label("gw2 window: unknown");
+                }
+            }
+        });
+    }
+}
 
-//         Ok(Self {
-//             jokolay_window_id,
-//             transient_for_atom,
-//             xc,
-//             // net_wm_pid_atom,
-//         })
-//     }
-//     pub fn set_transient_for(&self, parent_window: u32) -> Result<(), X11Error> {
-//         if let Ok(xst) = std::env::var("XDG_SESSION_TYPE") {
-//             if xst == "wayland" {
-//                 tracing::warn!("skipping transient_for because we are on wayland");
-//                 return Ok(());
-//             }
-//             if xst != "x11" {
-//                 tracing::warn!("xdg session type is neither wayland not x11: {xst}");
-//             }
-//         }
-//         assert_ne!(parent_window, 0);
-//         change_property(
-//             &self.xc,
-//             PropMode::REPLACE,
-//             self.jokolay_window_id,
-//             self.transient_for_atom,
-//             AtomEnum::WINDOW,
-//             32,
-//             1,
-//             &parent_window.to_ne_bytes(),
-//         )
-//         .map_err(|e| X11Error::TransientForError {
-//             source: e,
-//             parent: parent_window,
-//             child: self.jokolay_window_id,
-//         })?
-//         .check()
-//         .map_err(|e| X11Error::TransientForReplyError {
-//             source: e,
-//             parent: parent_window,
-//             child: self.jokolay_window_id,
-//         })?;
-//         Ok(())
-//     }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
 
-//     pub fn get_window_dimensions(&self, xid: u32) -> Result<[i32; 4]> {
-//         assert_ne!(xid, 0);
-//         let geometry = x11rb::protocol::xproto::get_geometry(&self.xc, xid)
-//             .into_diagnostic()
-//             .wrap_err("get geometry fn failed")?
-//             .reply()
-//             .into_diagnostic()
-//             .wrap_err("geometry reply is wrong")?;
-//         let translated_coordinates = x11rb::protocol::xproto::translate_coordinates(
-//             &self.xc,
-//             xid,
-//             geometry.root,
-//             geometry.x,
-//             geometry.y,
-//         )
-//         .into_diagnostic()
-//         .wrap_err("failed to translate coords")?
-//         .reply()
-//         .into_diagnostic()
-//         .wrap_err("translate coords reply error")?;
-//         let x_outer = translated_coordinates.dst_x as i32;
-//         let y_outer = translated_coordinates.dst_y as i32;
-//         let width = geometry.width;
-//         let height = geometry.height;
+    /// size of the `LinkedMem` struct of mumble, which is what raw bridges copy
+    const LINKED_MEM_SIZE: usize = 5460;
 
-//         tracing::debug!(
-//             "translated_x: {}, translated_y: {}, width: {}, height: {}, geo_x: {}, geo_y: {}",
-//             x_outer,
-//             y_outer,
-//             width,
-//             height,
-//             geometry.x,
-//             geometry.y
-//         );
-//         Ok([x_outer, y_outer, width as _, height as _])
-//     }
-//     // pub fn get_pid_from_xid(&self, xid: u32) -> Result<u32, String> {
-//     //     assert_ne!(xid, 0);
+    fn write_fixture(path: &Path, link: &CMumbleLink, size: usize) {
+        let mut bytes = vec![0u8; size.max(C_MUMBLE_LINK_SIZE_FULL)];
+        let link_bytes = unsafe {
+            std::slice::from_raw_parts(
+                link as *const CMumbleLink as *const u8,
+                C_MUMBLE_LINK_SIZE_FULL,
+            )
+        };
+        bytes[..C_MUMBLE_LINK_SIZE_FULL].copy_from_slice(link_bytes);
+        File::create(path).unwrap().write_all(&bytes).unwrap();
+    }
 
-//     //     let pid_prop = get_property(
-//     //         &self.xc,
-//     //         false,
-//     //         xid,
-//     //         self.net_wm_pid_atom,
-//     //         AtomEnum::CARDINAL,
-//     //         0,
-//     //         1,
-//     //     )
-//     //     .expect("coudn't get _NET_WM_PID property gw2")
-//     //     .reply()
-//     //     .expect("reply for _NET_WM_PID property gw2 ");
+    fn fixture_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("jokolink_test_{}_{name}", std::process::id()))
+    }
 
-//     //     if pid_prop.bytes_after != 0
-//     //         && pid_prop.format != 32
-//     //         && pid_prop.value_len != 1
-//     //         && pid_prop.value.len() != 4
-//     //     {
-//     //         panic!("invalid pid property {:#?}", pid_prop);
-//     //     }
-//     //     Ok(u32::from_ne_bytes(pid_prop.value.try_into().expect(
-//     //         "pid property value has a bytes length of less than 4",
-//     //     )))
-//     // }
-// }
-// pub fn get_frame_extents(xc: &RustConnection, xid: u32) -> Result<(u32, u32, u32, u32)> {
-//     assert_ne!(xid, 0);
-//     let net_frame_extents_atom = intern_atom(&self.xc, true, b"_NET_FRAME_EXTENTS")
-//         .expect("coudn't intern atom for _NET_FRAME_EXTENTS ")?
-//         .reply()
-//         .expect("reply for intern atom for _NET_FRAME_EXTENTS")?
-//         .atom;
-//     let frame_prop = get_property(
-//         &self.xc,
-//         false,
-//         xid,
-//         net_frame_extents_atom,
-//         AtomEnum::ANY,
-//         0,
-//         100,
-//     )
-//     .expect("coudn't get frame property gw2")?
-//     .reply()
-//     .expect("reply for frame property gw2")?;
+    #[test]
+    fn jokolink_layout_uses_timestamp() {
+        let path = fixture_path("jokolink");
+        let mut link = CMumbleLink {
+            ui_tick: 5,
+            ..Default::default()
+        };
+        link.context.client_pos_size = [10, 20, 800, 600];
+        link.context.timestamp = OffsetDateTime::now_utc()
+            .unix_timestamp_nanos()
+            .to_le_bytes();
+        write_fixture(&path, &link, C_MUMBLE_LINK_SIZE_FULL);
+        let mut backend = MumbleLinuxImpl::with_path(&path).unwrap();
+        backend.tick().unwrap();
+        assert_eq!(backend.layout(), LinkLayout::Jokolink);
+        assert!(backend.is_alive());
+        let cml = backend.get_cmumble_link();
+        assert_eq!(cml.ui_tick, 5);
+        assert_eq!(cml.context.client_pos_size, [10, 20, 800, 600]);
+        // jokolink stopped writing a while ago
+        link.context.timestamp = (OffsetDateTime::now_utc().unix_timestamp_nanos()
+            - Duration::from_secs(5).as_nanos() as i128)
+            .to_le_bytes();
+        write_fixture(&path, &link, C_MUMBLE_LINK_SIZE_FULL);
+        backend.tick().unwrap();
+        assert!(!backend.is_alive());
+        assert_eq!(backend.get_cmumble_link().ui_tick, 0);
+        std::fs::remove_file(path).unwrap();
+    }
 
-//     if frame_prop.bytes_after != 0 {
-//         bail!(
-//             "bytes after in frame property is {}",
-//             frame_prop.bytes_after
-//         );
-//     }
-//     if frame_prop.format != 32 {
-//         bail!("frame_prop format is {}", frame_prop.format);
-//     }
-//     if frame_prop.value_len != 4 {
-//         bail!("frame_prop value_len is {}", frame_prop.value_len);
-//     }
-//     if frame_prop.value.len() != 16 {
-//         bail!("frame_prop.value.len() is {}", frame_prop.value.len());
-//     }
-//     // avoid bytemuck dependency and just do this raw.
-//     let mut arr = [0u8; 4];
-//     arr.copy_from_slice(&frame_prop.value[0..4]);
-//     let left_border = u32::from_ne_bytes(arr);
-//     arr.copy_from_slice(&frame_prop.value[4..8]);
-//     let right_border = u32::from_ne_bytes(arr);
-//     arr.copy_from_slice(&frame_prop.value[8..12]);
-//     let top_border = u32::from_ne_bytes(arr);
-//     arr.copy_from_slice(&frame_prop.value[12..16]);
-//     let bottom_border = u32::from_ne_bytes(arr);
-//     Ok((left_border, right_border, top_border, bottom_border))
-// }
+    #[test]
+    fn raw_layout_uses_ui_tick() {
+        let path = fixture_path("raw");
+        let mut link = CMumbleLink {
+            ui_tick: 1,
+            ..Default::default()
+        };
+        write_fixture(&path, &link, LINKED_MEM_SIZE);
+        let mut backend = MumbleLinuxImpl::with_path(&path).unwrap();
+        // don't depend on the x server of the machine running the tests
+        backend.screen_pos_size = Some([0, 0, 1920, 1080]);
+        backend.tick().unwrap();
+        assert_eq!(backend.layout(), LinkLayout::Raw);
+        // ui_tick didn't change since we started
+        assert!(!backend.is_alive());
+        link.ui_tick = 2;
+        write_fixture(&path, &link, LINKED_MEM_SIZE);
+        backend.tick().unwrap();
+        assert!(backend.is_alive());
+        let cml = backend.get_cmumble_link();
+        assert_eq!(cml.ui_tick, 2);
+        assert_eq!(cml.context.client_pos_size, [0, 0, 1920, 1080]);
+        // no x server. must not make up a zero sized screen
+        backend.screen_pos_size = None;
+        assert_eq!(backend.get_cmumble_link().context.client_pos_size, [0; 4]);
+        // ui_tick stopped changing
+        backend.ui_tick_changed_at = Some(Instant::now() - LIVENESS_TIMEOUT * 2);
+        backend.tick().unwrap();
+        assert!(!backend.is_alive());
+        std::fs::remove_file(path).unwrap();
+    }
 
-// pub fn get_gw2_pid(&mut self) -> Result<u32> {
-//     assert_ne!(self.gw2_window_handle, 0);
-//     let pid_atom = x11rb::protocol::xproto::intern_atom(&self.&self.xc, true, b"_NET_WM_PID")
-//         .expect("could not intern atom '_NET_WM_PID'")?
-//         .reply()
-//         .expect("reply error while interning '_NET_WM_PID'.")?
-//         .atom;
-//     let reply = x11rb::protocol::xproto::get_property(
-//         &self.&self.xc,
-//         false,
-//         self.gw2_window_handle,
-//         pid_atom,
-//         x11rb::protocol::xproto::AtomEnum::CARDINAL,
-//         0,
-//         1,
-//     )
-//     .expect("could not request '_NET_WM_PID' for gw2 window handle ")?
-//     .reply()
-//     .expect("the reply for '_NET_WM_PID' of gw2 handle ")?;
+    #[test]
+    fn read_mode_from_str() {
+        assert_eq!(ReadMode::parse("frame"), Some(ReadMode::PerFrame));
+        assert_eq!(ReadMode::parse("inotify"), Some(ReadMode::Inotify));
+        assert_eq!(
+            ReadMode::parse("poll"),
+            Some(ReadMode::Poll(DEFAULT_POLL_INTERVAL))
+        );
+        assert_eq!(
+            ReadMode::parse("poll:16"),
+            Some(ReadMode::Poll(Duration::from_millis(16)))
+        );
+        assert_eq!(ReadMode::parse("poll:fast"), None);
+    }
 
-//     let pid_format = 32;
-//     if pid_format != reply.format {
-//         bail!("pid_format is not 32. so, type is wrong");
-//     }
-//     let pid_buffer_size = 4;
-//     if pid_buffer_size != reply.value.len() {
-//         bail!("pid_buffer is not 4 bytes");
-//     }
-//     let value_len = 1;
-//     if value_len != reply.value_len {
-//         bail!("pid reply's value_len is not 1");
-//     }
-//     let remaining_bytes_len = 0;
-//     if remaining_bytes_len != reply.bytes_after {
-//         bail!("we still have too many bytes remaining after reading '_NET_WM_PID'");
-//     }
-//     let mut buffer = [0u8; 4];
-//     buffer.copy_from_slice(&reply.value);
-//     Ok(u32::from_ne_bytes(buffer))
-// }
+    #[test]
+    fn background_modes_publish_latest_link() {
+        for (name, mode) in [
+            ("inotify", ReadMode::Inotify),
+            ("poll", ReadMode::Poll(Duration::from_millis(1))),
+        ] {
+            let path = fixture_path(name);
+            let mut link = CMumbleLink {
+                ui_tick: 1,
+                ..Default::default()
+            };
+            write_fixture(&path, &link, LINKED_MEM_SIZE);
+            let mut backend = MumbleLinuxImpl::with_mode(&path, mode).unwrap();
+            backend.screen_pos_size = Some([0, 0, 1920, 1080]);
+            backend.tick().unwrap();
+            assert_eq!(backend.previous_ui_tick, 1, "{mode:?}");
+            link.ui_tick = 2;
+            write_fixture(&path, &link, LINKED_MEM_SIZE);
+            let started = Instant::now();
+            while !backend.is_alive() {
+                assert!(
+                    started.elapsed() < Duration::from_secs(5),
+                    "{mode:?} never saw the update"
+                );
+                std::thread::sleep(Duration::from_millis(1));
+                backend.tick().unwrap();
+            }
+            assert_eq!(backend.get_cmumble_link().ui_tick, 2, "{mode:?}");
+            drop(backend);
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
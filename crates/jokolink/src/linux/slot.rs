@@ -0,0 +1,94 @@
+use std::sync::atomic::{fence, AtomicU32, AtomicU64, Ordering};
+
+use crate::ctypes::C_MUMBLE_LINK_SIZE_FULL;
+
+const WORDS: usize = C_MUMBLE_LINK_SIZE_FULL / 4;
+// the link is just a bunch of u32/f32 (and a few u8/u16 arrays which add upto multiples of 4), so it always fits in words
+const _: () = assert!(C_MUMBLE_LINK_SIZE_FULL.is_multiple_of(4));
+
+/// Holds the latest link bytes written by a background thread. A seqlock, so the reader (render loop) never waits for a lock
+/// and the writer never waits for the reader. If the reader catches the writer in the middle of a write, it just reads again.
+/// There must only be a single writer.
+pub(crate) struct LinkSlot {
+    /// odd while the writer is writing. incremented by 2 for every write
+    seq: AtomicU64,
+    words: Box<[AtomicU32]>,
+}
+
+impl Default for LinkSlot {
+    fn default() -> Self {
+        Self {
+            seq: AtomicU64::new(0),
+            words: (0..WORDS).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+}
+
+impl LinkSlot {
+    /// only call from a single writer thread
+    pub fn write(&self, bytes: &[u8; C_MUMBLE_LINK_SIZE_FULL]) {
+        let seq = self.seq.load(Ordering::Relaxed);
+        self.seq.store(seq + 1, Ordering::Relaxed);
+        fence(Ordering::Release);
+        for (word, chunk) in self.words.iter().zip(bytes.chunks_exact(4)) {
+            word.store(
+                u32::from_ne_bytes(chunk.try_into().unwrap()),
+                Ordering::Relaxed,
+            );
+        }
+        self.seq.store(seq + 2, Ordering::Release);
+    }
+    /// copies the latest complete write into `bytes` and returns the number of writes so far
+    pub fn read(&self, bytes: &mut [u8; C_MUMBLE_LINK_SIZE_FULL]) -> u64 {
+        loop {
+            let before = self.seq.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                std::hint::spin_loop();
+                continue;
+            }
+            for (word, chunk) in self.words.iter().zip(bytes.chunks_exact_mut(4)) {
+                chunk.copy_from_slice(&word.load(Ordering::Relaxed).to_ne_bytes());
+            }
+            fence(Ordering::Acquire);
+            if self.seq.load(Ordering::Relaxed) == before {
+                return before / 2;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn reads_are_never_torn() {
+        let slot = Arc::new(LinkSlot::default());
+        let writer = {
+            let slot = slot.clone();
+            std::thread::spawn(move || {
+                for value in 0..2000u32 {
+                    let mut bytes = [0u8; C_MUMBLE_LINK_SIZE_FULL];
+                    for chunk in bytes.chunks_exact_mut(4) {
+                        chunk.copy_from_slice(&value.to_ne_bytes());
+                    }
+                    slot.write(&bytes);
+                }
+            })
+        };
+        let mut bytes = [0u8; C_MUMBLE_LINK_SIZE_FULL];
+        let mut previous = 0;
+        while !writer.is_finished() {
+            let version = slot.read(&mut bytes);
+            assert!(version >= previous);
+            previous = version;
+            // every word must be from the same write
+            let first = &bytes[..4];
+            assert!(bytes.chunks_exact(4).all(|chunk| chunk == first));
+        }
+        writer.join().unwrap();
+        assert_eq!(slot.read(&mut bytes), 2000);
+        assert_eq!(&bytes[..4], &1999u32.to_ne_bytes());
+    }
+}
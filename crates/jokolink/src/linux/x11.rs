@@ -0,0 +1,386 @@
+//! Talks to the x server about the gw2 window. jokolink dll writes the x11 window id (xid) of gw2 into the link,
+//! which we can use to get the geometry of the gw2 window or to make jokolay a transient window of gw2.
+//! wine only uses x11 (xwayland on wayland), but jokolay might be a native wayland window. So, on wayland, we just
+//! skip all of this and use the window geometry that jokolink gets from wine.
+
+use miette::{Context, IntoDiagnostic, Result};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, ConnectionExt, PropMode};
+use x11rb::rust_connection::RustConnection;
+use x11rb::wrapper::ConnectionExt as _;
+
+use crate::ctypes::CMumbleLink;
+use crate::UIState;
+
+/// env var to configure the x11 window discovery. comma separated list of `transient` and `geometry`, or `off`. default is `transient`
+pub const X11_ENV: &str = "JOKOLINK_X11";
+/// how often we ask the x server for the geometry of gw2 window
+const GEOMETRY_REFRESH: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct X11Options {
+    /// set `WM_TRANSIENT_FOR` of jokolay window to the focused gw2 window, so that window managers keep jokolay above gw2
+    pub transient_for: bool,
+    /// use the geometry of the gw2 window from x server instead of the `client_pos_size` that jokolink dll gets from wine
+    pub geometry: bool,
+}
+
+impl Default for X11Options {
+    fn default() -> Self {
+        Self {
+            transient_for: true,
+            geometry: false,
+        }
+    }
+}
+
+impl X11Options {
+    /// reads [X11_ENV]
+    pub fn from_env() -> Self {
+        match std::env::var(X11_ENV) {
+            Ok(options) => Self::parse(&options),
+            Err(_) => Self::default(),
+        }
+    }
+    fn parse(options: &str) -> Self {
+        let mut result = Self {
+            transient_for: false,
+            geometry: false,
+        };
+        for option in options.split(',').map(str::trim) {
+            match option {
+                "transient" => result.transient_for = true,
+                "geometry" => result.geometry = true,
+                "off" | "" => {}
+                option => warn!(option, "unknown x11 option"),
+            }
+        }
+        result
+    }
+    pub fn is_enabled(&self) -> bool {
+        self.transient_for || self.geometry
+    }
+}
+
+/// borders and titlebar added by the window manager around a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FrameExtents {
+    pub left: u32,
+    pub right: u32,
+    pub top: u32,
+    pub bottom: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowGeometry {
+    /// x, y, width, height of the window (without decorations) relative to the top left corner of the root window
+    pub pos_size: [i32; 4],
+    /// None if the window manager doesn't set `_NET_FRAME_EXTENTS` (or there's no window manager)
+    pub frame_extents: Option<FrameExtents>,
+}
+
+pub struct X11Connection {
+    xc: RustConnection,
+    root: u32,
+    net_frame_extents_atom: u32,
+}
+
+impl X11Connection {
+    /// whether we are running in a wayland session. xwayland coordinates are useless there
+    pub fn is_wayland() -> bool {
+        std::env::var("XDG_SESSION_TYPE")
+            .map(|session| session == "wayland")
+            .unwrap_or_default()
+    }
+    /// connects to the x server from `DISPLAY` env
+    pub fn connect() -> Result<Self> {
+        let (xc, screen_num) = RustConnection::connect(None)
+            .into_diagnostic()
+            .wrap_err("failed to connect to x server")?;
+        let root = xc.setup().roots[screen_num].root;
+        let net_frame_extents_atom = xc
+            .intern_atom(false, b"_NET_FRAME_EXTENTS")
+            .into_diagnostic()
+            .wrap_err("failed to intern _NET_FRAME_EXTENTS atom")?
+            .reply()
+            .into_diagnostic()
+            .wrap_err("intern _NET_FRAME_EXTENTS atom reply error")?
+            .atom;
+        Ok(Self {
+            xc,
+            root,
+            net_frame_extents_atom,
+        })
+    }
+    /// position (relative to root window) and size of the window with `xid`. fails if the window doesn't exist
+    pub fn get_window_geometry(&self, xid: u32) -> Result<WindowGeometry> {
+        let geometry = self
+            .xc
+            .get_geometry(xid)
+            .into_diagnostic()
+            .wrap_err("get geometry fn failed")?
+            .reply()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("geometry reply error. window {xid} might not exist"))?;
+        // geometry.x/y are relative to the parent, which is the frame window of the window manager. so, we translate the origin to root
+        let translated = self
+            .xc
+            .translate_coordinates(xid, self.root, 0, 0)
+            .into_diagnostic()
+            .wrap_err("failed to translate coords")?
+            .reply()
+            .into_diagnostic()
+            .wrap_err("translate coords reply error")?;
+        Ok(WindowGeometry {
+            pos_size: [
+                translated.dst_x as i32,
+                translated.dst_y as i32,
+                geometry.width as i32,
+                geometry.height as i32,
+            ],
+            frame_extents: self.get_frame_extents(xid)?,
+        })
+    }
+    /// `_NET_FRAME_EXTENTS` set by the window manager on the window with `xid`
+    pub fn get_frame_extents(&self, xid: u32) -> Result<Option<FrameExtents>> {
+        let reply = self
+            .xc
+            .get_property(
+                false,
+                xid,
+                self.net_frame_extents_atom,
+                AtomEnum::CARDINAL,
+                0,
+                4,
+            )
+            .into_diagnostic()
+            .wrap_err("failed to get _NET_FRAME_EXTENTS property")?
+            .reply()
+            .into_diagnostic()
+            .wrap_err("_NET_FRAME_EXTENTS property reply error")?;
+        let Some(value) = reply.value32() else {
+            return Ok(None);
+        };
+        let value: Vec<u32> = value.collect();
+        Ok(match value.as_slice() {
+            &[left, right, top, bottom] => Some(FrameExtents {
+                left,
+                right,
+                top,
+                bottom,
+            }),
+            _ => None,
+        })
+    }
+    /// sets `WM_TRANSIENT_FOR` property of `child` window to `parent` window
+    pub fn set_transient_for(&self, child: u32, parent: u32) -> Result<()> {
+        self.xc
+            .change_property32(
+                PropMode::REPLACE,
+                child,
+                AtomEnum::WM_TRANSIENT_FOR,
+                AtomEnum::WINDOW,
+                &[parent],
+            )
+            .into_diagnostic()
+            .wrap_err("failed to change WM_TRANSIENT_FOR property")?
+            .check()
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to set {child} as transient for {parent}"))?;
+        Ok(())
+    }
+    /// the `WM_TRANSIENT_FOR` property of `child` window
+    pub fn get_transient_for(&self, child: u32) -> Result<Option<u32>> {
+        let reply = self
+            .xc
+            .get_property(
+                false,
+                child,
+                AtomEnum::WM_TRANSIENT_FOR,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )
+            .into_diagnostic()
+            .wrap_err("failed to get WM_TRANSIENT_FOR property")?
+            .reply()
+            .into_diagnostic()
+            .wrap_err("WM_TRANSIENT_FOR property reply error")?;
+        Ok(reply.value32().and_then(|mut value| value.next()))
+    }
+}
+
+/// Follows the gw2 window with the xid from jokolink and does whatever [X11Options] asks for
+pub(crate) struct X11Tracker {
+    pub xc: X11Connection,
+    pub options: X11Options,
+    pub jokolay_window_id: Option<u32>,
+    /// the gw2 window that we set as transient for jokolay window
+    pub transient_parent: Option<u32>,
+    /// latest geometry of the gw2 window. None if we failed to get it
+    pub geometry: Option<WindowGeometry>,
+    geometry_xid: u32,
+    geometry_updated_at: Option<Instant>,
+}
+
+impl X11Tracker {
+    /// None if x11 is disabled, we are on wayland or can't connect to the x server
+    pub fn new(options: X11Options, jokolay_window_id: Option<u32>) -> Option<Self> {
+        if !options.is_enabled() {
+            return None;
+        }
+        if X11Connection::is_wayland() {
+            info!("skipping x11 window discovery on wayland. using the window geometry from wine");
+            return None;
+        }
+        match X11Connection::connect() {
+            Ok(xc) => Some(Self {
+                xc,
+                options,
+                jokolay_window_id,
+                transient_parent: None,
+                geometry: None,
+                geometry_xid: 0,
+                geometry_updated_at: None,
+            }),
+            Err(e) => {
+                warn!(?e, "x11 window discovery is disabled");
+                None
+            }
+        }
+    }
+    /// `link` must be from jokolink dll and alive
+    pub fn update(&mut self, link: &mut CMumbleLink) {
+        let xid = link.context.xid;
+        if xid == 0 {
+            return;
+        }
+        let game_has_focus = link.context.ui_state & UIState::GameHasFocus as u32 != 0;
+        if let Some(jokolay_window_id) = self.jokolay_window_id {
+            // when multiboxing, jokolay follows the focused gw2 client
+            if self.options.transient_for && game_has_focus && self.transient_parent != Some(xid) {
+                // even if it fails, we don't want to try again every frame
+                self.transient_parent = Some(xid);
+                match self.xc.set_transient_for(jokolay_window_id, xid) {
+                    Ok(_) => info!(jokolay_window_id, xid, "set jokolay as transient for gw2"),
+                    Err(e) => warn!(?e, "failed to set jokolay as transient for gw2"),
+                }
+            }
+        }
+        if self.options.geometry {
+            let stale = self.geometry_xid != xid
+                || self
+                    .geometry_updated_at
+                    .map(|updated_at| updated_at.elapsed() > GEOMETRY_REFRESH)
+                    .unwrap_or(true);
+            if stale {
+                let log = self.geometry_xid != xid || self.geometry.is_some();
+                self.geometry = self
+                    .xc
+                    .get_window_geometry(xid)
+                    .map_err(|e| {
+                        if log {
+                            warn!(
+                                ?e,
+                                xid,
+                                "failed to get gw2 window geometry. using the geometry from wine"
+                            );
+                        }
+                    })
+                    .ok();
+                self.geometry_xid = xid;
+                self.geometry_updated_at = Some(Instant::now());
+            }
+            if let Some(geometry) = self.geometry {
+                link.context.client_pos_size = geometry.pos_size;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x11rb::protocol::xproto::{CreateWindowAux, WindowClass};
+
+    #[test]
+    fn options_from_str() {
+        assert_eq!(X11Options::parse("off"), X11Options::parse(""));
+        assert!(!X11Options::parse("off").is_enabled());
+        assert_eq!(
+            X11Options::parse("transient, geometry"),
+            X11Options {
+                transient_for: true,
+                geometry: true
+            }
+        );
+        assert_eq!(
+            X11Options::parse("geometry"),
+            X11Options {
+                transient_for: false,
+                geometry: true
+            }
+        );
+    }
+
+    fn create_window(xc: &X11Connection, pos_size: [i16; 4]) -> u32 {
+        let window = xc.xc.generate_id().unwrap();
+        xc.xc
+            .create_window(
+                x11rb::COPY_DEPTH_FROM_PARENT,
+                window,
+                xc.root,
+                pos_size[0],
+                pos_size[1],
+                pos_size[2] as u16,
+                pos_size[3] as u16,
+                0,
+                WindowClass::INPUT_OUTPUT,
+                x11rb::COPY_FROM_PARENT,
+                &CreateWindowAux::new(),
+            )
+            .unwrap()
+            .check()
+            .unwrap();
+        xc.xc.map_window(window).unwrap().check().unwrap();
+        window
+    }
+
+    #[test]
+    #[ignore = "needs an x server. run with `xvfb-run cargo test -p jokolink -- --ignored`"]
+    fn window_geometry_and_transient_for() {
+        let xc = X11Connection::connect().unwrap();
+        let gw2 = create_window(&xc, [10, 20, 800, 600]);
+        let jokolay = create_window(&xc, [0, 0, 100, 100]);
+        let geometry = xc.get_window_geometry(gw2).unwrap();
+        assert_eq!(geometry.pos_size, [10, 20, 800, 600]);
+        // no window manager in xvfb
+        assert_eq!(geometry.frame_extents, None);
+        assert!(xc.get_window_geometry(gw2 + 100).is_err());
+
+        let mut tracker = X11Tracker {
+            xc,
+            options: X11Options {
+                transient_for: true,
+                geometry: true,
+            },
+            jokolay_window_id: Some(jokolay),
+            transient_parent: None,
+            geometry: None,
+            geometry_xid: 0,
+            geometry_updated_at: None,
+        };
+        let mut link = CMumbleLink::default();
+        link.context.xid = gw2;
+        link.context.client_pos_size = [0, 0, 1920, 1080];
+        // gw2 is not focused yet
+        tracker.update(&mut link);
+        assert_eq!(tracker.xc.get_transient_for(jokolay).unwrap(), None);
+        assert_eq!(link.context.client_pos_size, [10, 20, 800, 600]);
+        link.context.ui_state = UIState::GameHasFocus as u32;
+        tracker.update(&mut link);
+        assert_eq!(tracker.xc.get_transient_for(jokolay).unwrap(), Some(gw2));
+    }
+}
@@ -1,5 +1,7 @@
 use enumflags2::BitFlags;
-use jokoapi::end_point::{mounts::Mount, races::Race};
+use jokoapi::end_point::{
+    mounts::Mount, professions::Profession, races::Race, specializations::Specialization,
+};
 use miette::bail;
 use serde::{Deserialize, Serialize};
 
@@ -187,8 +189,8 @@ pub struct CMumbleContext {
     pub dpi: i32,
     /// This is the client (gw2 window's viewport/surface) position and area. This tells jokolay where to position and size itself to match gw2 window.
     pub client_pos_size: [i32; 4],
-    /// to make the struct the right size. everything upto now is 120 bytes, so this rounds upto 256 bytes.
-    pub padding: [u8; 96],
+    /// to make the struct the right size. everything upto now is 132 bytes, so this rounds upto 256 bytes.
+    pub padding: [u8; 124],
 }
 impl Default for CMumbleContext {
     fn default() -> Self {
@@ -213,7 +215,7 @@ impl Default for CMumbleContext {
             mount_index: Default::default(),
             timestamp: Default::default(),
             // window_pos_size: Default::default(),
-            padding: [0; 96],
+            padding: [0; 124],
             xid: Default::default(),
             // window_pos_size_without_borders: Default::default(),
             dpi_scaling: Default::default(),
@@ -299,6 +301,12 @@ impl CIdentity {
             _ => return None,
         })
     }
+    pub fn get_profession(&self) -> Option<Profession> {
+        Profession::try_from_mumble_link(self.profession)
+    }
+    pub fn get_spec(&self) -> Option<Specialization> {
+        Specialization::try_from_mumble_link(self.spec)
+    }
     pub fn get_race(&self) -> Option<Race> {
         Some(match self.race {
             0 => Race::ASURA,
@@ -5,7 +5,9 @@ use std::net::IpAddr;
 
 use enumflags2::{bitflags, BitFlags};
 use glam::{IVec2, Vec3};
-use jokoapi::end_point::mounts::Mount;
+use jokoapi::end_point::{
+    mounts::Mount, professions::Profession, races::Race, specializations::Specialization,
+};
 use num_derive::FromPrimitive;
 use num_derive::ToPrimitive;
 use serde::Deserialize;
@@ -26,6 +28,17 @@ pub struct MumbleLink {
     pub f_camera_front: Vec3,
     /// The name of the character
     pub name: String,
+    /// core profession of the character
+    pub profession: Option<Profession>,
+    /// third (elite) specialization of the character. None if there's no specialization in the third slot
+    pub spec: Option<Specialization>,
+    pub race: Option<Race>,
+    /// useless field from pre-megaserver days. just the shard_id
+    pub world_id: u32,
+    /// Team color per API:2/colors (0 = white). mostly useful in wvw/pvp
+    pub team_color_id: u32,
+    /// whether the character has a commander tag active
+    pub commander: bool,
     /// API:2/maps
     pub map_id: u32,
     pub map_type: u32,
@@ -36,9 +49,8 @@ pub struct MumbleLink {
     pub build_id: u32,
     /// The fields until now are provided for mumble.
     /// The rest of the data from here is what gw2 provides for the benefit of addons.
-    /// This is the current UI state of the game. refer to [UIState]
-    /// // Bitmask: Bit 1 = IsMapOpen, Bit 2 = IsCompassTopRight, Bit 3 = DoesCompassHaveRotationEnabled, Bit 4 = Game has focus, Bit 5 = Is in Competitive game mode, Bit 6 = Textbox has focus, Bit 7 = Is in Combat
-    pub ui_state: u32,
+    /// This is the current UI state of the game. refer to [UIState] or use the helpers like [Self::is_map_open]
+    pub ui_state: BitFlags<UIState>,
     pub compass_width: u16,    // pixels
     pub compass_height: u16,   // pixels
     pub compass_rotation: f32, // radians
@@ -79,6 +91,8 @@ pub struct MumbleLink {
     pub client_size: IVec2,
     /// changes since last mumble link update
     pub changes: BitFlags<MumbleChanges>,
+    /// name of the mumble link this data was read from. differs between gw2 clients when multiboxing
+    pub link_name: String,
 }
 impl Default for MumbleLink {
     fn default() -> Self {
@@ -89,6 +103,12 @@ impl Default for MumbleLink {
             cam_pos: Default::default(),
             f_camera_front: Default::default(),
             name: Default::default(),
+            profession: Default::default(),
+            spec: Default::default(),
+            race: Default::default(),
+            world_id: Default::default(),
+            team_color_id: Default::default(),
+            commander: Default::default(),
             map_id: Default::default(),
             map_type: Default::default(),
             server_address: std::net::Ipv4Addr::UNSPECIFIED.into(),
@@ -113,9 +133,37 @@ impl Default for MumbleLink {
             client_pos: Default::default(),
             client_size: Default::default(),
             changes: Default::default(),
+            link_name: Default::default(),
         }
     }
 }
+impl MumbleLink {
+    /// the world map is open and covers the whole gw2 window
+    pub fn is_map_open(&self) -> bool {
+        self.ui_state.contains(UIState::IsMapOpen)
+    }
+    pub fn is_compass_top_right(&self) -> bool {
+        self.ui_state.contains(UIState::IsCompassTopRight)
+    }
+    pub fn is_compass_rotation_enabled(&self) -> bool {
+        self.ui_state
+            .contains(UIState::DoesCompassHaveRotationEnabled)
+    }
+    pub fn game_has_focus(&self) -> bool {
+        self.ui_state.contains(UIState::GameHasFocus)
+    }
+    /// pvp or wvw
+    pub fn in_competitive_mode(&self) -> bool {
+        self.ui_state.contains(UIState::InCompetitiveGamemode)
+    }
+    /// the player is typing into a textbox like chat. so, keyboard input belongs to gw2
+    pub fn textbox_focused(&self) -> bool {
+        self.ui_state.contains(UIState::TextboxFocus)
+    }
+    pub fn in_combat(&self) -> bool {
+        self.ui_state.contains(UIState::IsInCombat)
+    }
+}
 /// These flags represent the changes in mumble link compared to previous values
 #[bitflags]
 #[repr(u32)]
@@ -126,6 +174,17 @@ pub enum MumbleChanges {
     Character = 1 << 2,
     WindowPosition = 1 << 3,
     WindowSize = 1 << 4,
+    Profession = 1 << 5,
+    Spec = 1 << 6,
+    Mount = 1 << 7,
+    /// world map was opened or closed
+    MapOpen = 1 << 8,
+    /// entered or left combat
+    Combat = 1 << 9,
+    /// gw2 gained or lost focus
+    GameFocus = 1 << 10,
+    /// a textbox (eg: chat) gained or lost focus
+    TextboxFocus = 1 << 11,
 }
 
 /// represents the ui scale set in settings -> graphics options -> interface size
@@ -0,0 +1,416 @@
+//! Recording and replaying of raw mumble link data, so that we can work on jokolay without running gw2.
+//!
+//! The recording file is
+//! 1. a header: [RECORDING_MAGIC], the format version (u32) and the size of each frame (u32)
+//! 2. followed by frames: the time since the start of the recording in nanoseconds (u64) and the raw bytes of [CMumbleLink]
+//!
+//! All numbers are little endian. We only record a frame when `ui_tick` changes, so idle time (loading screens etc..) doesn't take any space.
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use miette::{bail, Context, IntoDiagnostic, Result};
+use tracing::info;
+
+use crate::{
+    ctypes::{CMumbleLink, C_MUMBLE_LINK_SIZE_FULL},
+    MumbleBackend,
+};
+
+pub const RECORDING_MAGIC: [u8; 8] = *b"JOKOMREC";
+pub const RECORDING_VERSION: u32 = 1;
+/// path of the recording to replay instead of the live mumble link
+pub const REPLAY_ENV: &str = "JOKOLINK_REPLAY";
+/// playback speed of the replay. `2.0` plays twice as fast
+pub const REPLAY_SPEED_ENV: &str = "JOKOLINK_REPLAY_SPEED";
+/// restart the replay from the beginning when it ends. `1` or `true`
+pub const REPLAY_LOOP_ENV: &str = "JOKOLINK_REPLAY_LOOP";
+/// path of the file to record the mumble link into
+pub const RECORD_ENV: &str = "JOKOLINK_RECORD";
+
+fn link_to_bytes(link: &CMumbleLink) -> &[u8] {
+    // CMumbleLink is plain old data. padding bytes are written as they are and ignored when reading back
+    unsafe {
+        std::slice::from_raw_parts(
+            link as *const CMumbleLink as *const u8,
+            C_MUMBLE_LINK_SIZE_FULL,
+        )
+    }
+}
+
+fn link_from_bytes(bytes: &[u8]) -> CMumbleLink {
+    assert_eq!(bytes.len(), C_MUMBLE_LINK_SIZE_FULL);
+    unsafe { std::ptr::read_unaligned(bytes.as_ptr() as *const CMumbleLink) }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RecordedFrame {
+    /// time since the start of the recording
+    pub time: Duration,
+    pub link: CMumbleLink,
+}
+
+/// Writes the mumble link frames into a recording
+pub struct MumbleRecorder<W: Write = BufWriter<File>> {
+    writer: W,
+    start: Instant,
+    previous_ui_tick: u32,
+    frames: usize,
+}
+
+impl MumbleRecorder {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to create mumble recording file {path:?}"))?;
+        info!(?path, "recording mumble link");
+        Self::new(BufWriter::new(file))
+    }
+}
+
+impl<W: Write> MumbleRecorder<W> {
+    pub fn new(mut writer: W) -> Result<Self> {
+        writer
+            .write_all(&RECORDING_MAGIC)
+            .and_then(|_| writer.write_all(&RECORDING_VERSION.to_le_bytes()))
+            .and_then(|_| writer.write_all(&(C_MUMBLE_LINK_SIZE_FULL as u32).to_le_bytes()))
+            .into_diagnostic()
+            .wrap_err("failed to write recording header")?;
+        Ok(Self {
+            writer,
+            start: Instant::now(),
+            previous_ui_tick: 0,
+            frames: 0,
+        })
+    }
+    /// records the link if its ui_tick changed since the last recorded frame
+    pub fn record(&mut self, link: &CMumbleLink) -> Result<()> {
+        self.record_at(self.start.elapsed(), link)
+    }
+    /// records the link with the given time since the start of the recording
+    pub fn record_at(&mut self, time: Duration, link: &CMumbleLink) -> Result<()> {
+        if link.ui_tick == 0 || link.ui_tick == self.previous_ui_tick {
+            return Ok(());
+        }
+        self.previous_ui_tick = link.ui_tick;
+        self.writer
+            .write_all(&(time.as_nanos() as u64).to_le_bytes())
+            .and_then(|_| self.writer.write_all(link_to_bytes(link)))
+            .into_diagnostic()
+            .wrap_err("failed to write recording frame")?;
+        self.frames += 1;
+        Ok(())
+    }
+    /// number of frames recorded until now
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+    /// flushes and returns the writer
+    pub fn finish(mut self) -> Result<W> {
+        self.writer
+            .flush()
+            .into_diagnostic()
+            .wrap_err("failed to flush recording")?;
+        Ok(self.writer)
+    }
+}
+
+/// reads all the frames of a recording
+pub fn read_recording(mut reader: impl Read) -> Result<Vec<RecordedFrame>> {
+    let mut header = [0u8; 16];
+    reader
+        .read_exact(&mut header)
+        .into_diagnostic()
+        .wrap_err("failed to read recording header")?;
+    if header[..8] != RECORDING_MAGIC {
+        bail!("not a mumble recording");
+    }
+    let version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+    if version != RECORDING_VERSION {
+        bail!("unsupported recording version {version}");
+    }
+    let frame_size = u32::from_le_bytes(header[12..16].try_into().unwrap()) as usize;
+    if frame_size != C_MUMBLE_LINK_SIZE_FULL {
+        bail!("recording frame size is {frame_size}, but mumble link size is {C_MUMBLE_LINK_SIZE_FULL}");
+    }
+    let mut frames = vec![];
+    let mut buffer = vec![0u8; 8 + frame_size];
+    loop {
+        match reader.read_exact(&mut buffer) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => {
+                return Err(e)
+                    .into_diagnostic()
+                    .wrap_err("failed to read recording frame")
+            }
+        }
+        frames.push(RecordedFrame {
+            time: Duration::from_nanos(u64::from_le_bytes(buffer[..8].try_into().unwrap())),
+            link: link_from_bytes(&buffer[8..]),
+        });
+    }
+    Ok(frames)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ReplayOptions {
+    /// playback speed. `1.0` is the original speed
+    pub speed: f32,
+    /// start again from the beginning after the last frame
+    pub looping: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            looping: false,
+        }
+    }
+}
+
+impl ReplayOptions {
+    /// the fastest playback speed. also the limit of the speed in the gui
+    pub const MAX_SPEED: f32 = 100.0;
+    /// reads [REPLAY_SPEED_ENV] and [REPLAY_LOOP_ENV]
+    pub fn from_env() -> Self {
+        let mut options = Self::default();
+        if let Ok(speed) = std::env::var(REPLAY_SPEED_ENV) {
+            match speed.parse::<f32>() {
+                Ok(value) if value.is_finite() => {
+                    options.speed = value.clamp(0.0, Self::MAX_SPEED);
+                    if options.speed != value {
+                        tracing::warn!(speed, options.speed, "replay speed is out of range");
+                    }
+                }
+                Ok(_) => tracing::warn!(speed, "replay speed must be a finite number"),
+                Err(e) => tracing::warn!(?e, speed, "invalid replay speed"),
+            }
+        }
+        if let Ok(looping) = std::env::var(REPLAY_LOOP_ENV) {
+            options.looping = looping == "1" || looping.eq_ignore_ascii_case("true");
+        }
+        options
+    }
+}
+
+/// Plays back a recording as if it is the live mumble link. Same interface as the platform backends.
+pub struct MumbleReplay {
+    frames: Vec<RecordedFrame>,
+    pub options: ReplayOptions,
+    /// current playback time of the recording
+    position: Duration,
+    /// index of the frame at the current position
+    current: usize,
+    last_tick: Option<Instant>,
+    pub paused: bool,
+}
+
+impl MumbleReplay {
+    pub fn open(path: &Path, options: ReplayOptions) -> Result<Self> {
+        let file = File::open(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to open mumble recording {path:?}"))?;
+        let frames = read_recording(BufReader::new(file))?;
+        info!(?path, frames = frames.len(), "replaying mumble recording");
+        Ok(Self::new(frames, options))
+    }
+    pub fn new(frames: Vec<RecordedFrame>, options: ReplayOptions) -> Self {
+        Self {
+            frames,
+            options,
+            position: Duration::ZERO,
+            current: 0,
+            last_tick: None,
+            paused: false,
+        }
+    }
+    /// total length of the recording
+    pub fn duration(&self) -> Duration {
+        self.frames
+            .last()
+            .map(|frame| frame.time)
+            .unwrap_or_default()
+    }
+    pub fn position(&self) -> Duration {
+        self.position
+    }
+    /// jumps to the given time of the recording
+    pub fn seek(&mut self, position: Duration) {
+        self.position = position.min(self.duration());
+        // frame with the latest time which is not after the position
+        self.current = self
+            .frames
+            .partition_point(|frame| frame.time <= self.position)
+            .saturating_sub(1);
+    }
+    /// advances the playback by `elapsed` real time (scaled by speed)
+    pub fn advance(&mut self, elapsed: Duration) {
+        if self.paused || self.frames.is_empty() {
+            return;
+        }
+        let mut position = self.position
+            + elapsed.mul_f32(self.options.speed.clamp(0.0, ReplayOptions::MAX_SPEED));
+        let duration = self.duration();
+        if position > duration {
+            position = if self.options.looping && !duration.is_zero() {
+                Duration::from_nanos((position.as_nanos() % duration.as_nanos()) as u64)
+            } else {
+                duration
+            };
+        }
+        self.seek(position);
+    }
+}
+
+impl MumbleBackend for MumbleReplay {
+    fn tick(&mut self) -> Result<()> {
+        let now = Instant::now();
+        if let Some(last_tick) = self.last_tick {
+            self.advance(now - last_tick);
+        }
+        self.last_tick = Some(now);
+        Ok(())
+    }
+    /// alive until the last frame is played. like gw2 being closed when the recording ends
+    fn is_alive(&self) -> bool {
+        !self.frames.is_empty() && (self.options.looping || self.position < self.duration())
+    }
+    fn get_cmumble_link(&mut self) -> CMumbleLink {
+        if self.is_alive() {
+            self.frames[self.current].link
+        } else {
+            Default::default()
+        }
+    }
+    /// controls for the playback
+    fn gui(&mut self, ui: &mut egui::Ui) {
+        ui.collapsing("replay", |ui| {
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.paused, "paused");
+                ui.checkbox(&mut self.options.looping, "loop");
+                ui.add(
+                    egui::DragValue::new(&mut self.options.speed)
+                        .clamp_range(0.0..=ReplayOptions::MAX_SPEED)
+                        .speed(0.1)
+                        .prefix("speed: "),
+                );
+            });
+            let mut position = self.position.as_secs_f32();
+            if ui
+                .add(
+                    egui::Slider::new(&mut position, 0.0..=self.duration().as_secs_f32())
+                        .suffix(" s"),
+                )
+                .changed()
+            {
+                self.seek(Duration::from_secs_f32(position));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(ui_tick: u32, map_id: u32) -> CMumbleLink {
+        let mut link = CMumbleLink {
+            ui_tick,
+            ..Default::default()
+        };
+        link.context.map_id = map_id;
+        link.f_avatar_position = [ui_tick as f32, 0.0, 0.0];
+        link
+    }
+
+    fn recording() -> Vec<RecordedFrame> {
+        let mut recorder = MumbleRecorder::new(vec![]).unwrap();
+        for (second, ui_tick) in [(0, 1), (1, 2), (1, 2), (2, 3), (3, 0), (4, 4)] {
+            recorder
+                .record_at(Duration::from_secs(second), &link(ui_tick, 15))
+                .unwrap();
+        }
+        assert_eq!(recorder.frames(), 4);
+        read_recording(recorder.finish().unwrap().as_slice()).unwrap()
+    }
+
+    #[test]
+    fn recording_round_trip() {
+        let frames = recording();
+        let ticks: Vec<u32> = frames.iter().map(|frame| frame.link.ui_tick).collect();
+        // duplicate and zero ticks are skipped
+        assert_eq!(ticks, vec![1, 2, 3, 4]);
+        assert_eq!(frames[3].time, Duration::from_secs(4));
+        assert_eq!(frames[3].link.context.map_id, 15);
+        assert_eq!(frames[3].link.f_avatar_position, [4.0, 0.0, 0.0]);
+        assert!(read_recording(&b"not a recording"[..]).is_err());
+    }
+
+    #[test]
+    fn replay_with_speed_and_seek() {
+        let mut replay = MumbleReplay::new(
+            recording(),
+            ReplayOptions {
+                speed: 2.0,
+                looping: false,
+            },
+        );
+        assert_eq!(replay.get_cmumble_link().ui_tick, 1);
+        replay.advance(Duration::from_millis(600));
+        assert_eq!(replay.get_cmumble_link().ui_tick, 2);
+        replay.seek(Duration::from_millis(2500));
+        assert_eq!(replay.get_cmumble_link().ui_tick, 3);
+        replay.seek(Duration::ZERO);
+        assert_eq!(replay.get_cmumble_link().ui_tick, 1);
+        // dead after the recording ends
+        replay.advance(Duration::from_secs(10));
+        assert!(!replay.is_alive());
+        assert_eq!(replay.get_cmumble_link().ui_tick, 0);
+    }
+
+    #[test]
+    fn replay_loops() {
+        let mut replay = MumbleReplay::new(
+            recording(),
+            ReplayOptions {
+                speed: 1.0,
+                looping: true,
+            },
+        );
+        replay.advance(Duration::from_millis(5500));
+        assert!(replay.is_alive());
+        assert_eq!(replay.position(), Duration::from_millis(1500));
+        assert_eq!(replay.get_cmumble_link().ui_tick, 2);
+    }
+
+    #[test]
+    fn speed_from_env_is_finite_and_clamped() {
+        for (value, speed) in [
+            ("2.5", 2.5),
+            ("inf", 1.0),
+            ("NaN", 1.0),
+            ("1e30", 100.0),
+            ("-3", 0.0),
+        ] {
+            std::env::set_var(REPLAY_SPEED_ENV, value);
+            assert_eq!(ReplayOptions::from_env().speed, speed, "{value}");
+        }
+        std::env::remove_var(REPLAY_SPEED_ENV);
+        // a huge speed set directly (eg: by the gui) doesn't panic either
+        let mut replay = MumbleReplay::new(
+            recording(),
+            ReplayOptions {
+                speed: f32::MAX,
+                looping: true,
+            },
+        );
+        replay.advance(Duration::from_secs(1));
+        assert!(replay.is_alive());
+    }
+}
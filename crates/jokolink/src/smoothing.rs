@@ -0,0 +1,288 @@
+//! Smooths the camera and player positions between gw2 ticks.
+//! gw2 updates the link at its own framerate, while jokolay might render at a higher framerate (or just out of sync).
+//! So, the markers would jitter whenever the camera moves. The smoother predicts where the camera is right now,
+//! based on how fast it moved between the previous two ticks. When a new tick arrives, the prediction error is blended away
+//! over a short duration instead of making the camera jump.
+
+use enumflags2::BitFlags;
+use glam::Vec3;
+use std::time::{Duration, Instant};
+
+use crate::{MumbleChanges, MumbleLink};
+
+/// set to `1` or `true` to enable smoothing in [crate::MumbleManager]
+pub const SMOOTHING_ENV: &str = "JOKOLINK_SMOOTHING";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothingOptions {
+    /// stop predicting after this long without a new tick. Also, if two ticks are further apart than this,
+    /// we don't know how fast we are moving (loading screens, lag spikes etc..).
+    pub max_extrapolation: Duration,
+    /// how long it takes to blend away the prediction error when a new tick arrives
+    pub correction_time: Duration,
+    /// if the player or camera moves more than this (in meters) in a single tick, it is a teleport (waypoint, portal etc..)
+    /// and we just snap to the new position
+    pub teleport_distance: f32,
+}
+
+impl Default for SmoothingOptions {
+    fn default() -> Self {
+        Self {
+            max_extrapolation: Duration::from_millis(100),
+            correction_time: Duration::from_millis(50),
+            teleport_distance: 10.0,
+        }
+    }
+}
+
+impl SmoothingOptions {
+    /// default options if smoothing is enabled with [SMOOTHING_ENV]
+    pub fn from_env() -> Option<Self> {
+        let enabled = std::env::var(SMOOTHING_ENV).ok()?;
+        (enabled == "1" || enabled.eq_ignore_ascii_case("true")).then(Self::default)
+    }
+}
+
+/// The parts of the link that we smooth
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Pose {
+    cam_pos: Vec3,
+    f_camera_front: Vec3,
+    player_pos: Vec3,
+}
+
+impl Pose {
+    fn from_link(link: &MumbleLink) -> Self {
+        Self {
+            cam_pos: link.cam_pos,
+            f_camera_front: link.f_camera_front,
+            player_pos: link.player_pos,
+        }
+    }
+    fn map(self, other: Self, f: impl Fn(Vec3, Vec3) -> Vec3) -> Self {
+        Self {
+            cam_pos: f(self.cam_pos, other.cam_pos),
+            f_camera_front: f(self.f_camera_front, other.f_camera_front),
+            player_pos: f(self.player_pos, other.player_pos),
+        }
+    }
+    fn scale(self, factor: f32) -> Self {
+        self.map(self, |a, _| a * factor)
+    }
+}
+
+/// The latest tick that we got from gw2 and when we got it
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    ui_tick: u32,
+    map_id: u32,
+    time: Instant,
+    pose: Pose,
+}
+
+/// Interpolates/Extrapolates the positions of the link every frame. Use [crate::MumbleManager::set_smoothing] to let the manager do it.
+#[derive(Debug, Clone)]
+pub struct MumbleSmoother {
+    pub options: SmoothingOptions,
+    latest: Option<Sample>,
+    /// per second
+    velocity: Pose,
+    /// what we displayed minus the actual pose when the latest tick arrived. fades to zero over [SmoothingOptions::correction_time]
+    correction: Pose,
+}
+
+impl MumbleSmoother {
+    pub fn new(options: SmoothingOptions) -> Self {
+        Self {
+            options,
+            latest: None,
+            velocity: Default::default(),
+            correction: Default::default(),
+        }
+    }
+    /// forget the previous ticks. the next link will be used as is
+    pub fn reset(&mut self) {
+        self.latest = None;
+    }
+    /// replaces the positions in `link` with the smoothed positions at `now`. call this every frame
+    pub fn smooth(&mut self, link: &mut MumbleLink, now: Instant) {
+        let pose = Pose::from_link(link);
+        let sample = Sample {
+            ui_tick: link.ui_tick,
+            map_id: link.map_id,
+            time: now,
+            pose,
+        };
+        match self.latest {
+            Some(latest) if latest.ui_tick == link.ui_tick && latest.map_id == link.map_id => {
+                // no new data from gw2. just keep predicting
+            }
+            Some(latest) if !self.is_teleport(&latest, &sample, link.changes) => {
+                let displayed = self.pose_at(now);
+                let elapsed = now.saturating_duration_since(latest.time).as_secs_f32();
+                self.velocity = if elapsed > 0.0 {
+                    pose.map(latest.pose, |new, old| (new - old) / elapsed)
+                } else {
+                    self.velocity
+                };
+                self.correction = displayed.map(pose, |displayed, actual| displayed - actual);
+                self.latest = Some(sample);
+            }
+            _ => {
+                self.velocity = Default::default();
+                self.correction = Default::default();
+                self.latest = Some(sample);
+            }
+        }
+        let smoothed = self.pose_at(now);
+        link.cam_pos = smoothed.cam_pos;
+        link.f_camera_front = smoothed
+            .f_camera_front
+            .try_normalize()
+            .unwrap_or(pose.f_camera_front);
+        link.player_pos = smoothed.player_pos;
+    }
+    fn is_teleport(
+        &self,
+        latest: &Sample,
+        sample: &Sample,
+        changes: BitFlags<MumbleChanges>,
+    ) -> bool {
+        changes.intersects(MumbleChanges::Map | MumbleChanges::Character)
+            || latest.map_id != sample.map_id
+            || sample.time.saturating_duration_since(latest.time) > self.options.max_extrapolation
+            || latest.pose.player_pos.distance(sample.pose.player_pos)
+                > self.options.teleport_distance
+            || latest.pose.cam_pos.distance(sample.pose.cam_pos) > self.options.teleport_distance
+    }
+    fn pose_at(&self, now: Instant) -> Pose {
+        let Some(latest) = self.latest else {
+            return Default::default();
+        };
+        let elapsed = now
+            .saturating_duration_since(latest.time)
+            .min(self.options.max_extrapolation);
+        let fade = if self.options.correction_time.is_zero() {
+            0.0
+        } else {
+            1.0 - (elapsed.as_secs_f32() / self.options.correction_time.as_secs_f32()).min(1.0)
+        };
+        let predicted = latest
+            .pose
+            .map(self.velocity.scale(elapsed.as_secs_f32()), |pose, delta| {
+                pose + delta
+            });
+        predicted.map(self.correction.scale(fade), |pose, correction| {
+            pose + correction
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(20);
+
+    fn link(ui_tick: u32, x: f32) -> MumbleLink {
+        MumbleLink {
+            ui_tick,
+            map_id: 15,
+            player_pos: Vec3::new(x, 0.0, 0.0),
+            cam_pos: Vec3::new(x, 5.0, -5.0),
+            f_camera_front: Vec3::Z,
+            ..Default::default()
+        }
+    }
+
+    /// walks 0.1 meters per tick (5 m/s) along x and returns the time of the last tick
+    fn walk(smoother: &mut MumbleSmoother, start: Instant, ticks: u32) -> Instant {
+        let mut now = start;
+        for tick in 1..=ticks {
+            now = start + TICK * tick;
+            smoother.smooth(&mut link(tick, tick as f32 * 0.1), now);
+        }
+        now
+    }
+
+    fn no_correction() -> SmoothingOptions {
+        SmoothingOptions {
+            correction_time: Duration::ZERO,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn extrapolates_between_ticks() {
+        let mut smoother = MumbleSmoother::new(no_correction());
+        let start = Instant::now();
+        let last = walk(&mut smoother, start, 5);
+        // half a tick later, with the same ui_tick
+        let mut current = link(5, 0.5);
+        smoother.smooth(&mut current, last + TICK / 2);
+        assert!(
+            (current.player_pos.x - 0.55).abs() < 1e-4,
+            "{}",
+            current.player_pos
+        );
+        assert!(
+            (current.cam_pos.x - 0.55).abs() < 1e-4,
+            "{}",
+            current.cam_pos
+        );
+        assert_eq!(current.f_camera_front, Vec3::Z);
+        // gw2 froze. don't fly away
+        let mut current = link(5, 0.5);
+        smoother.smooth(&mut current, last + Duration::from_secs(1));
+        assert!(
+            (current.player_pos.x - 1.0).abs() < 1e-4,
+            "{}",
+            current.player_pos
+        );
+    }
+
+    #[test]
+    fn blends_prediction_error() {
+        let mut smoother = MumbleSmoother::new(SmoothingOptions::default());
+        let start = Instant::now();
+        let last = walk(&mut smoother, start, 5);
+        let mut before = link(5, 0.5);
+        smoother.smooth(&mut before, last + TICK);
+        // player stopped. the new tick must not make the marker jump back
+        let mut stopped = link(6, 0.5);
+        smoother.smooth(&mut stopped, last + TICK);
+        assert!(before.player_pos.abs_diff_eq(stopped.player_pos, 1e-4));
+        // and after the correction time, we reach the actual position
+        let mut settled = link(6, 0.5);
+        smoother.smooth(&mut settled, last + TICK + Duration::from_millis(60));
+        assert!(
+            (settled.player_pos.x - 0.5).abs() < 1e-4,
+            "{}",
+            settled.player_pos
+        );
+    }
+
+    #[test]
+    fn snaps_on_teleport_and_map_change() {
+        let mut smoother = MumbleSmoother::new(SmoothingOptions::default());
+        let start = Instant::now();
+        let last = walk(&mut smoother, start, 5);
+        // waypoint
+        let mut teleported = link(6, 500.0);
+        smoother.smooth(&mut teleported, last + TICK);
+        assert_eq!(teleported.player_pos, Vec3::new(500.0, 0.0, 0.0));
+        let mut later = link(6, 500.0);
+        smoother.smooth(&mut later, last + TICK * 2);
+        assert_eq!(later.player_pos, Vec3::new(500.0, 0.0, 0.0));
+        // a small step, but on a different map
+        let mut new_map = link(7, 500.1);
+        new_map.map_id = 50;
+        new_map.changes = MumbleChanges::Map.into();
+        smoother.smooth(&mut new_map, last + TICK * 3);
+        assert_eq!(new_map.player_pos, Vec3::new(500.1, 0.0, 0.0));
+        let mut later = link(7, 500.1);
+        later.map_id = 50;
+        smoother.smooth(&mut later, last + TICK * 4);
+        assert_eq!(later.player_pos, Vec3::new(500.1, 0.0, 0.0));
+    }
+}
@@ -0,0 +1,167 @@
+//! A fake mumble backend which plays a script of steps. one step per tick.
+//! Useful for testing [crate::MumbleManager] or the rest of jokolay without gw2 or a recording.
+
+use std::collections::VecDeque;
+
+use enumflags2::BitFlags;
+use glam::Vec3;
+use miette::Result;
+
+use crate::{
+    ctypes::{CIdentity, CMumbleLink},
+    MumbleBackend, UIState,
+};
+
+/// A single step of the script. Every step except [SyntheticStep::Freeze] increments ui_tick, like a frame of gw2
+#[derive(Debug, Clone)]
+pub enum SyntheticStep {
+    /// moves the player to the position, with the camera looking at the player from behind
+    MoveTo(Vec3),
+    /// loads into a different map
+    ChangeMap(u32),
+    /// switches to a different character
+    ChangeCharacter(String),
+    /// moves/resizes the gw2 window. x, y, width, height
+    ClientPosSize([i32; 4]),
+    /// writes the raw string into the identity field instead of the serialized identity
+    RawIdentity(String),
+    /// gw2 window gains or loses focus
+    Focus(bool),
+    /// sets or unsets a ui state. eg: opening the map or focusing chat
+    UiState(UIState, bool),
+    /// mounts up. uses the mumble link index of the mount and 0 to dismount
+    Mount(u8),
+    /// ui_tick doesn't change. like a loading screen or character select
+    Freeze,
+    /// gw2 is closed. the backend is not alive anymore
+    Close,
+    /// gw2 is started again
+    Start,
+}
+
+pub struct SyntheticMumble {
+    pub link: CMumbleLink,
+    pub identity: CIdentity,
+    /// when set, this is written into the identity field instead of [Self::identity]
+    pub raw_identity: Option<String>,
+    pub alive: bool,
+    script: VecDeque<SyntheticStep>,
+}
+
+impl Default for SyntheticMumble {
+    /// A character standing at the origin of map 15 (Queensdale) in a focused 1920x1080 window
+    fn default() -> Self {
+        let mut link = CMumbleLink {
+            ui_version: 2,
+            ..Default::default()
+        };
+        link.context.map_id = 15;
+        link.context.client_pos_size = [0, 0, 1920, 1080];
+        link.context.dpi = 96;
+        link.context.ui_state = UIState::GameHasFocus as u32;
+        link.f_camera_front = [0.0, 0.0, 1.0];
+        link.f_avatar_front = [0.0, 0.0, 1.0];
+        Self {
+            link,
+            identity: CIdentity {
+                name: "Synthetic Character".to_string(),
+                map_id: 15,
+                fov: 1.0,
+                uisz: 1,
+                ..Default::default()
+            },
+            raw_identity: None,
+            alive: true,
+            script: Default::default(),
+        }
+    }
+}
+
+impl SyntheticMumble {
+    pub fn new(script: impl IntoIterator<Item = SyntheticStep>) -> Self {
+        let mut synthetic = Self::default();
+        synthetic.push(script);
+        synthetic
+    }
+    /// walks the player through the points, taking `steps` ticks between each pair of points
+    pub fn walk(points: &[Vec3], steps: usize) -> Self {
+        let mut script = vec![];
+        for pair in points.windows(2) {
+            for step in 0..steps {
+                script.push(SyntheticStep::MoveTo(
+                    pair[0].lerp(pair[1], step as f32 / steps as f32),
+                ));
+            }
+        }
+        script.extend(points.last().copied().map(SyntheticStep::MoveTo));
+        Self::new(script)
+    }
+    /// appends the steps to the end of the script
+    pub fn push(&mut self, steps: impl IntoIterator<Item = SyntheticStep>) {
+        self.script.extend(steps);
+    }
+    /// number of steps left in the script
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+    fn set_ui_state(&mut self, state: UIState, value: bool) {
+        let mut ui_state = BitFlags::<UIState>::from_bits_truncate(self.link.context.ui_state);
+        ui_state.set(state, value);
+        self.link.context.ui_state = ui_state.bits();
+    }
+    fn apply(&mut self, step: SyntheticStep) {
+        match step {
+            SyntheticStep::MoveTo(position) => {
+                let front = Vec3::from(self.link.f_camera_front);
+                self.link.f_avatar_position = position.into();
+                self.link.f_camera_position = (position - front * 5.0 + Vec3::Y * 2.0).into();
+            }
+            SyntheticStep::ChangeMap(map_id) => {
+                self.link.context.map_id = map_id;
+                self.identity.map_id = map_id;
+            }
+            SyntheticStep::ChangeCharacter(name) => self.identity.name = name,
+            SyntheticStep::ClientPosSize(pos_size) => self.link.context.client_pos_size = pos_size,
+            SyntheticStep::RawIdentity(raw) => self.raw_identity = Some(raw),
+            SyntheticStep::Focus(focus) => self.set_ui_state(UIState::GameHasFocus, focus),
+            SyntheticStep::UiState(state, value) => self.set_ui_state(state, value),
+            SyntheticStep::Mount(index) => self.link.context.mount_index = index,
+            SyntheticStep::Freeze => return,
+            SyntheticStep::Close => {
+                self.alive = false;
+                self.link.ui_tick = 0;
+                return;
+            }
+            SyntheticStep::Start => self.alive = true,
+        }
+        self.link.ui_tick += 1;
+    }
+}
+
+impl MumbleBackend for SyntheticMumble {
+    fn tick(&mut self) -> Result<()> {
+        if let Some(step) = self.script.pop_front() {
+            self.apply(step);
+        }
+        Ok(())
+    }
+    fn is_alive(&self) -> bool {
+        self.alive
+    }
+    fn get_cmumble_link(&mut self) -> CMumbleLink {
+        if !self.alive {
+            return Default::default();
+        }
+        let json = match self.raw_identity.clone() {
+            Some(raw) => raw,
+            None => serde_json::to_string(&self.identity).expect("failed to serialize identity"),
+        };
+        let mut link = self.link;
+        link.identity = [0; 256];
+        // leave atleast one null terminator at the end
+        for (dst, src) in link.identity[..255].iter_mut().zip(json.encode_utf16()) {
+            *dst = src;
+        }
+        link
+    }
+}
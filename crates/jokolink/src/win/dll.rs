@@ -270,7 +270,10 @@ pub mod d3d11 {
         pub struct JokolinkConfig {
             pub loglevel: String,
             pub logdir: PathBuf,
-            pub mumble_link_name: String,
+            /// the names passed to gw2 clients with `-mumble`. one per client when multiboxing.
+            /// older configs have a single `mumble_link_name` string, which is still accepted
+            #[serde(alias = "mumble_link_name", deserialize_with = "one_or_many")]
+            pub mumble_link_names: Vec<String>,
             pub interval: u32,
             pub copy_dest_dir: PathBuf,
         }
@@ -280,13 +283,28 @@ pub mod d3d11 {
                 Self {
                     loglevel: "info".to_string(),
                     logdir: PathBuf::from("."),
-                    mumble_link_name: DEFAULT_MUMBLELINK_NAME.to_string(),
+                    mumble_link_names: vec![DEFAULT_MUMBLELINK_NAME.to_string()],
                     interval: 5,
                     copy_dest_dir: PathBuf::from("z:\\dev\\shm"),
                 }
             }
         }
 
+        fn one_or_many<'de, D: serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<Vec<String>, D::Error> {
+            #[derive(Deserialize)]
+            #[serde(untagged)]
+            enum OneOrMany {
+                One(String),
+                Many(Vec<String>),
+            }
+            Ok(match OneOrMany::deserialize(deserializer)? {
+                OneOrMany::One(name) => vec![name],
+                OneOrMany::Many(names) => names,
+            })
+        }
+
         pub fn wine_main(
             quit_request_receiver: Receiver<()>,
             quit_response_sender: SyncSender<()>,
@@ -391,7 +409,7 @@ pub mod d3d11 {
                 &config.loglevel, &config.logdir
             );
             info!("created app and initialized logging");
-            info!("the mumble link names: {:#?}", &config.mumble_link_name);
+            info!("the mumble link names: {:#?}", &config.mumble_link_names);
             info!(
                 "the mumble refresh interval in milliseconds: {:#?}",
                 refresh_inverval
@@ -401,45 +419,50 @@ pub mod d3d11 {
                 "the path to which we write mumble data: {:#?}",
                 &config.copy_dest_dir
             );
-            let mumble_key = config.mumble_link_name.clone();
+            // one shared memory and one shm file per link name
+            let mut links = vec![];
+            for mumble_key in config.mumble_link_names.iter() {
+                let dest_path = config.copy_dest_dir.join(mumble_key);
 
-            let dest_path = config.copy_dest_dir.join(&mumble_key);
+                // create a shared memory file in /dev/shm/mumble_link_key_name so that jokolay can mumble stuff from there.
+                info!(
+                    "creating the path to destination shm file: {:?}",
+                    &dest_path
+                );
 
-            // create a shared memory file in /dev/shm/mumble_link_key_name so that jokolay can mumble stuff from there.
-            info!(
-                "creating the path to destination shm file: {:?}",
-                &dest_path
-            );
-
-            let mut mfile = std::fs::File::options()
-                .write(true)
-                .create(true)
-                .open(&dest_path)
-                .into_diagnostic()
-                .wrap_err_with(|| {
-                    format!("failed to create shm file with path {:#?}", &dest_path)
-                })?;
-            // create shared memory using the mumble link key
-            let mut source = MumbleWinImpl::new(&mumble_key)?;
+                let mfile = std::fs::File::options()
+                    .write(true)
+                    .create(true)
+                    .open(&dest_path)
+                    .into_diagnostic()
+                    .wrap_err_with(|| {
+                        format!("failed to create shm file with path {:#?}", &dest_path)
+                    })?;
+                // create shared memory using the mumble link key
+                let source = MumbleWinImpl::new(mumble_key)?;
+                links.push((source, mfile));
+            }
 
             loop {
-                if let Err(e) = source.tick() {
-                    error!(?e, "mumble tick error");
-                }
-                let link = source.get_cmumble_link();
+                for (source, mfile) in links.iter_mut() {
+                    if let Err(e) = source.tick() {
+                        error!(?e, "mumble tick error");
+                    }
+                    let link = source.get_cmumble_link();
 
-                let buffer: [u8; C_MUMBLE_LINK_SIZE_FULL] =
-                    unsafe { std::ptr::read_volatile(&link as *const CMumbleLink as *const _) };
-                mfile
-                    .seek(SeekFrom::Start(0))
-                    .into_diagnostic()
-                    .wrap_err("could not seek to start of shared memory file due to error")?;
+                    let buffer: [u8; C_MUMBLE_LINK_SIZE_FULL] =
+                        unsafe { std::ptr::read_volatile(&link as *const CMumbleLink as *const _) };
+                    mfile
+                        .seek(SeekFrom::Start(0))
+                        .into_diagnostic()
+                        .wrap_err("could not seek to start of shared memory file due to error")?;
 
-                // write buffer to the file
-                mfile
-                    .write(&buffer)
-                    .into_diagnostic()
-                    .wrap_err("could not write to shared memory file due to error")?;
+                    // write buffer to the file
+                    mfile
+                        .write(&buffer)
+                        .into_diagnostic()
+                        .wrap_err("could not write to shared memory file due to error")?;
+                }
                 match quit_signal.try_recv() {
                     Ok(_) => {
                         println!("received quit signal. returning from wine_main()");
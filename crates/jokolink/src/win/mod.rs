@@ -703,6 +703,18 @@ fn get_client_rect_in_screen_coords(window_handle: HWND) -> Result<[i32; 4]> {
         ])
     }
 }
+// jokolink.dll uses the inherent methods directly, so the trait just forwards to them
+impl crate::MumbleBackend for MumbleWinImpl {
+    fn tick(&mut self) -> Result<()> {
+        MumbleWinImpl::tick(self)
+    }
+    fn is_alive(&self) -> bool {
+        MumbleWinImpl::is_alive(self)
+    }
+    fn get_cmumble_link(&mut self) -> CMumbleLink {
+        MumbleWinImpl::get_cmumble_link(self)
+    }
+}
 impl Drop for MumbleWinImpl {
     fn drop(&mut self) {
         unsafe {